@@ -0,0 +1,143 @@
+//! Witness generation, keygen, proving, verification and aggregation of JWT proofs.
+//!
+//! Keygen, proving and aggregation take seconds to minutes, so their groups take the
+//! fewest samples criterion allows. `cargo bench -- prove` runs a single group.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use halo2_jwt::aggregation::circuit::JwtAggregationCircuit;
+use halo2_jwt::aggregation::JwtAggregator;
+use halo2_jwt::circuit::JwtCircuit;
+use halo2_jwt::layout::measure;
+use halo2_jwt::precompute::PreComputed;
+use halo2_jwt::proof::{gen_snark, prove_jwt, Backend, Kzg, ProvingConfig};
+use halo2_jwt::sha256::{padded_blockwords, Sha256, Table16Chip, Table16Config};
+use halo2_jwt::verifier::verify_jwt_proof;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::bn256::Fr;
+use snark_verifier_sdk::{gen_pk, halo2::gen_srs};
+
+const CREDENTIAL: &str = "twitter|337834122";
+/// Payload sizes in bytes, from a short token to a long one.
+const TOKEN_SIZES: [usize; 3] = [128, 512, 1024];
+
+/// A payload of `len` bytes containing the credential.
+fn token(len: usize) -> String {
+    let head = format!("{{\"iss\":\"https://dev-9h47ajc9.us.auth0.com/\",\"sub\":\"{}\",\"aud\":\"123\",\"pad\":\"", CREDENTIAL);
+    let tail = "\"}";
+    format!("{}{}{}", head, "x".repeat(len - head.len() - tail.len()), tail)
+}
+
+fn circuit(len: usize) -> JwtCircuit {
+    JwtCircuit::new(PreComputed::new(&token(len), CREDENTIAL))
+}
+
+fn precompute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("precompute");
+    for len in TOKEN_SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(len), &token(len), |b, jwt| {
+            b.iter(|| PreComputed::new(jwt, CREDENTIAL))
+        });
+    }
+    group.finish();
+}
+
+/// SHA-256 of "abc", a single block.
+#[derive(Clone, Default)]
+struct SingleBlock;
+
+impl Circuit<Fr> for SingleBlock {
+    type Config = Table16Config<Fr>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        Table16Chip::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        let table16_chip = Table16Chip::construct(config.clone());
+        Table16Chip::load(config, &mut layouter)?;
+
+        Sha256::digest(table16_chip, layouter.namespace(|| "sha256"), &padded_blockwords(b"abc"))?;
+
+        Ok(())
+    }
+}
+
+fn table16(c: &mut Criterion) {
+    let k = measure::<Fr, _>(&SingleBlock).unwrap().min_k();
+    let params = Kzg::setup(k);
+    let pk = Kzg::keygen(&params, &SingleBlock).unwrap();
+
+    let mut group = c.benchmark_group("table16");
+    group.sample_size(10);
+    group.bench_function("single_block_prove", |b| {
+        b.iter(|| Kzg::prove(&params, &pk, SingleBlock, &[], &ProvingConfig::NATIVE).unwrap())
+    });
+    group.finish();
+}
+
+fn keygen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keygen");
+    group.sample_size(10);
+    for len in TOKEN_SIZES {
+        let circuit = circuit(len);
+        let params = Kzg::setup(circuit.min_params().unwrap().k);
+        group.bench_with_input(BenchmarkId::from_parameter(len), &circuit, |b, circuit| {
+            b.iter(|| Kzg::keygen(&params, circuit).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn prove_and_verify(c: &mut Criterion) {
+    let config = ProvingConfig::NATIVE;
+
+    for len in TOKEN_SIZES {
+        let circuit = circuit(len);
+        let params = Kzg::setup(circuit.min_params().unwrap().k);
+        let pk = Kzg::keygen(&params, &circuit).unwrap();
+        let public_inputs = circuit.public_inputs::<Fr>();
+
+        c.benchmark_group("prove")
+            .sample_size(10)
+            .bench_with_input(BenchmarkId::from_parameter(len), &circuit, |b, circuit| {
+                b.iter(|| prove_jwt::<Kzg>(&params, &pk, circuit.clone(), &config).unwrap())
+            });
+
+        let proof = prove_jwt::<Kzg>(&params, &pk, circuit, &config).unwrap();
+        c.benchmark_group("verify")
+            .bench_with_input(BenchmarkId::from_parameter(len), &proof, |b, proof| {
+                b.iter(|| verify_jwt_proof::<Kzg>(&params, pk.get_vk(), &public_inputs, proof).unwrap())
+            });
+    }
+}
+
+fn aggregation(c: &mut Criterion) {
+    let circuit = circuit(TOKEN_SIZES[0]);
+    let params_app = gen_srs(circuit.min_params().unwrap().k);
+    let pk_app = gen_pk(&params_app, &circuit, None);
+    let snark = gen_snark(&params_app, &pk_app, circuit, None, &ProvingConfig::RECURSIVE).unwrap();
+
+    // the layout does not depend on the SRS, so the application one measures it
+    let k_agg = measure::<Fr, _>(&JwtAggregationCircuit::new(&params_app, vec![snark.clone()]).unwrap())
+        .unwrap()
+        .min_k();
+    let aggregator = JwtAggregator::new(gen_srs(k_agg), 1, snark.clone(), None).unwrap();
+
+    let mut group = c.benchmark_group("aggregation");
+    group.sample_size(10);
+    group.bench_function("aggregate_1", |b| {
+        b.iter(|| aggregator.aggregate(vec![snark.clone()], None).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, precompute, table16, keygen, prove_and_verify, aggregation);
+criterion_main!(benches);
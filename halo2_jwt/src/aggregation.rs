@@ -0,0 +1,196 @@
+//! Aggregation of a variable number of `JwtCircuit` snarks into one.
+//!
+//! The verifying key of an aggregation circuit depends on how many snarks it verifies,
+//! so a [`JwtAggregator`] always verifies `capacity` of them, filling the free slots
+//! with a dummy snark. The dummy has to be a valid proof for the same circuit params
+//! as the real ones, e.g. of a token issued for that purpose.
+//!
+//! Application snarks are expected to be generated with [`ProvingConfig::RECURSIVE`].
+//! The instances of every aggregated snark, dummies included, are forwarded as
+//! instances of the aggregate by [`JwtAggregationCircuit`].
+//!
+//! Snarks can also be folded one at a time with a [`JwtChain`], which keeps a commitment
+//! to the credential and a count of the distinct tokens across steps.
+
+pub mod circuit;
+pub mod recursion;
+
+use std::path::Path;
+
+use halo2_proofs::{plonk::ProvingKey, poly::kzg::commitment::ParamsKZG};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use snark_verifier_sdk::{gen_pk, Snark};
+
+use crate::bundle::JwtPublicInputs;
+use crate::error::JwtError;
+use crate::proof::{gen_snark, ProvingConfig};
+use circuit::JwtAggregationCircuit;
+use recursion::preprocessed_digest;
+pub use recursion::{ChainState, JwtChain};
+
+/// An aggregate of JWT proofs.
+#[derive(Debug, Clone)]
+pub struct AggregatedJwtProofs {
+    /// The aggregation snark.
+    pub snark: Snark,
+    /// The public inputs of every aggregated proof, in order, without the padding.
+    pub inner_public_inputs: Vec<JwtPublicInputs>,
+    /// The KZG accumulator limbs, leading the instances of the aggregation snark.
+    pub accumulator: Vec<Fr>,
+}
+
+/// Aggregates up to `capacity` application snarks with a fixed proving key.
+pub struct JwtAggregator {
+    params: ParamsKZG<Bn256>,
+    capacity: usize,
+    dummy: Snark,
+    pk: ProvingKey<G1Affine>,
+}
+
+impl JwtAggregator {
+    /// Generates the aggregation proving key, or reads it from `pk_path` if it exists.
+    pub fn new(
+        params: ParamsKZG<Bn256>,
+        capacity: usize,
+        dummy: Snark,
+        pk_path: Option<&Path>,
+    ) -> Result<Self, JwtError> {
+        if capacity == 0 {
+            return Err(JwtError::ZeroCapacity);
+        }
+
+        let circuit = JwtAggregationCircuit::new(&params, vec![dummy.clone(); capacity])?;
+        let pk = gen_pk(&params, &circuit, pk_path);
+
+        Ok(Self { params, capacity, dummy, pk })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn pk(&self) -> &ProvingKey<G1Affine> {
+        &self.pk
+    }
+
+    /// Aggregates `snarks`, padded with the dummy snark up to the capacity. They must be
+    /// proven with the verifying key of the dummy.
+    pub fn aggregate(&self, snarks: Vec<Snark>, path: Option<&Path>) -> Result<AggregatedJwtProofs, JwtError> {
+        let (snarks, inner_public_inputs) = pad(snarks, self.capacity, &self.dummy)?;
+        let circuit = JwtAggregationCircuit::new(&self.params, snarks)?;
+        let accumulator = circuit.accumulator().to_vec();
+
+        let snark = gen_snark(&self.params, &self.pk, circuit, path, &ProvingConfig::RECURSIVE)?;
+
+        Ok(AggregatedJwtProofs {
+            snark,
+            inner_public_inputs,
+            accumulator,
+        })
+    }
+}
+
+/// Pads `snarks` with `dummy` up to `capacity`, returning them with the public inputs of
+/// the ones before the padding. The aggregation key is generated for the protocol of
+/// `dummy`, so a snark of another key is rejected.
+fn pad(snarks: Vec<Snark>, capacity: usize, dummy: &Snark) -> Result<(Vec<Snark>, Vec<JwtPublicInputs>), JwtError> {
+    if snarks.len() > capacity {
+        return Err(JwtError::CapacityExceeded {
+            capacity,
+            count: snarks.len(),
+        });
+    }
+    let digest = preprocessed_digest(&dummy.protocol);
+    if snarks.iter().any(|snark| {
+        snark.protocol.num_instance != dummy.protocol.num_instance || preprocessed_digest(&snark.protocol) != digest
+    }) {
+        return Err(JwtError::ParamsMismatch);
+    }
+
+    let inner_public_inputs = snarks
+        .iter()
+        .map(|snark| match snark.instances.as_slice() {
+            [instances] => Ok(JwtPublicInputs::from_instances(instances)?),
+            _ => Err(JwtError::InvalidPublicInputs),
+        })
+        .collect::<Result<Vec<_>, JwtError>>()?;
+
+    let padding = capacity - snarks.len();
+    let snarks = snarks
+        .into_iter()
+        .chain(std::iter::repeat(dummy.clone()).take(padding))
+        .collect();
+
+    Ok((snarks, inner_public_inputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pad;
+    use crate::aggregation::circuit::{JwtAggregationCircuit, ACCUMULATOR_INSTANCES};
+    use crate::bundle::JwtPublicInputs;
+    use crate::circuit::JwtCircuit;
+    use crate::error::JwtError;
+    use crate::precompute::PreComputed;
+    use crate::proof::{gen_pk, gen_snark, gen_srs, ProvingConfig};
+
+    use halo2curves::bn256::Fr;
+    use snark_verifier_sdk::CircuitExt;
+
+    #[test]
+    fn pads_up_to_the_capacity() {
+        let circuits = ["123", "456", "789"].map(|aud| {
+            let jwt = format!(
+                "{{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"{}\"}}",
+                aud
+            );
+            JwtCircuit::new(PreComputed::new(&jwt, "twitter|337834122"))
+        });
+        let public_inputs = circuits.iter().map(|circuit| circuit.public_inputs::<Fr>()).collect::<Vec<_>>();
+
+        let params = gen_srs(circuits[0].dimensions().unwrap().min_k());
+        let pk = gen_pk(&params, &circuits[0]);
+        let [first, second, dummy] =
+            circuits.map(|circuit| gen_snark(&params, &pk, circuit, None, &ProvingConfig::RECURSIVE).unwrap());
+
+        let (padded, inner_public_inputs) = pad(vec![first.clone(), second.clone()], 4, &dummy).unwrap();
+        assert_eq!(padded.len(), 4);
+        for (snark, expected) in padded.iter().zip([&first, &second, &dummy, &dummy]) {
+            assert_eq!(snark.instances, expected.instances);
+            assert_eq!(snark.proof, expected.proof);
+        }
+
+        // One entry per real snark, in the order of the slots
+        assert_eq!(
+            inner_public_inputs,
+            public_inputs[..2]
+                .iter()
+                .map(|instances| JwtPublicInputs::from_instances(instances).unwrap())
+                .collect::<Vec<_>>()
+        );
+        let instances = JwtAggregationCircuit::new(&params, padded).unwrap().instances();
+        let slots = instances[0][ACCUMULATOR_INSTANCES..].chunks(public_inputs[0].len()).collect::<Vec<_>>();
+        let expected = [0, 1, 2, 2].map(|idx| &public_inputs[idx][..]);
+        assert_eq!(slots, expected);
+
+        let (padded, inner_public_inputs) = pad(vec![first.clone(), second.clone()], 2, &dummy).unwrap();
+        assert_eq!(padded.len(), 2);
+        assert_eq!(inner_public_inputs.len(), 2);
+
+        assert!(matches!(
+            pad(vec![first.clone(), second, first.clone()], 2, &dummy),
+            Err(JwtError::CapacityExceeded { capacity: 2, count: 3 })
+        ));
+
+        // A token of other params has another verifying key
+        let jwt = format!(
+            "{{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"pad\":\"{}\"}}",
+            "x".repeat(64)
+        );
+        let circuit = JwtCircuit::new(PreComputed::new(&jwt, "twitter|337834122"));
+        let params = gen_srs(circuit.dimensions().unwrap().min_k());
+        let pk = gen_pk(&params, &circuit);
+        let longer = gen_snark(&params, &pk, circuit, None, &ProvingConfig::RECURSIVE).unwrap();
+        assert!(matches!(pad(vec![first, longer], 2, &dummy), Err(JwtError::ParamsMismatch)));
+    }
+}
@@ -0,0 +1,412 @@
+//! An aggregation circuit that, unlike the SDK's `AggregationCircuit`, forwards the
+//! instances of the snarks it verifies as its own.
+//!
+//! The instances of the aggregate are the KZG accumulator limbs, followed by the
+//! instances of every inner snark in order. A verifier of the aggregate thus sees which
+//! digests every inner proof was verified against.
+
+use std::rc::Rc;
+
+use halo2_ecc::{integer::rns::Rns, BaseFieldEccChip, EccConfig};
+use halo2_maingate::{
+    AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig, RangeInstructions,
+    RegionCtx,
+};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+    poly::{commitment::ParamsProver, kzg::commitment::ParamsKZG},
+};
+use halo2curves::bn256::{Bn256, Fq, Fr, G1Affine};
+use itertools::Itertools;
+use rand::rngs::OsRng;
+use snark_verifier::{
+    loader::{self, native::NativeLoader},
+    pcs::{
+        kzg::{Bdfg21, KzgAccumulator, KzgAs, KzgSuccinctVerifyingKey, LimbsEncoding},
+        AccumulationScheme, AccumulationSchemeProver,
+    },
+    system::halo2::transcript::halo2::PoseidonTranscript as Halo2PoseidonTranscript,
+    util::arithmetic::fe_to_limbs,
+    verifier::{self, plonk::PlonkProtocol, SnarkVerifier},
+};
+use snark_verifier_sdk::{CircuitExt, Snark};
+
+use crate::error::JwtError;
+
+pub(super) const LIMBS: usize = 4;
+pub(super) const BITS: usize = 68;
+pub(super) const T: usize = 5;
+pub(super) const RATE: usize = 4;
+pub(super) const R_F: usize = 8;
+pub(super) const R_P: usize = 60;
+
+/// Number of accumulator limbs leading the instances of the aggregate.
+pub const ACCUMULATOR_INSTANCES: usize = 4 * LIMBS;
+
+pub(super) type As = KzgAs<Bn256, Bdfg21>;
+pub(super) type PlonkSuccinctVerifier =
+    verifier::plonk::PlonkSuccinctVerifier<As, LimbsEncoding<LIMBS, BITS>>;
+pub(super) type Svk = KzgSuccinctVerifyingKey<G1Affine>;
+pub(super) type EccChip = BaseFieldEccChip<G1Affine, LIMBS, BITS>;
+pub(super) type Halo2Loader<'a> = loader::halo2::Halo2Loader<'a, G1Affine, EccChip>;
+pub(super) type LoadedScalar<'a> = loader::halo2::Scalar<'a, G1Affine, EccChip>;
+pub(super) type LoadedAccumulator<'a> = KzgAccumulator<G1Affine, Rc<Halo2Loader<'a>>>;
+pub(super) type PoseidonTranscript<L, S> = Halo2PoseidonTranscript<G1Affine, L, S, T, RATE, R_F, R_P>;
+
+#[derive(Clone)]
+pub(super) struct SnarkWitness {
+    pub(super) protocol: PlonkProtocol<G1Affine>,
+    pub(super) instances: Vec<Vec<Value<Fr>>>,
+    pub(super) proof: Value<Vec<u8>>,
+}
+
+impl From<Snark> for SnarkWitness {
+    fn from(snark: Snark) -> Self {
+        Self {
+            protocol: snark.protocol,
+            instances: snark
+                .instances
+                .into_iter()
+                .map(|instances| instances.into_iter().map(Value::known).collect())
+                .collect(),
+            proof: Value::known(snark.proof),
+        }
+    }
+}
+
+impl SnarkWitness {
+    pub(super) fn without_witnesses(&self) -> Self {
+        Self {
+            protocol: self.protocol.clone(),
+            instances: self
+                .instances
+                .iter()
+                .map(|instances| vec![Value::unknown(); instances.len()])
+                .collect(),
+            proof: Value::unknown(),
+        }
+    }
+
+    pub(super) fn proof(&self) -> Value<&[u8]> {
+        self.proof.as_ref().map(Vec::as_slice)
+    }
+}
+
+/// Verifies `snark` natively up to the final pairing, failing if its proof does not
+/// parse or its instances do not fit its protocol.
+pub(super) fn succinct_verify_native(
+    svk: &Svk,
+    snark: &Snark,
+) -> Result<Vec<KzgAccumulator<G1Affine, NativeLoader>>, JwtError> {
+    let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(snark.proof.as_slice());
+    PlonkSuccinctVerifier::read_proof(svk, &snark.protocol, &snark.instances, &mut transcript)
+        .and_then(|proof| PlonkSuccinctVerifier::verify(svk, &snark.protocol, &snark.instances, &proof))
+        .map_err(|_| JwtError::ProofRejected)
+}
+
+/// Folds accumulators natively, returning the accumulator and the folding proof.
+pub(super) fn accumulate_native(
+    accumulators: &[KzgAccumulator<G1Affine, NativeLoader>],
+) -> Result<(KzgAccumulator<G1Affine, NativeLoader>, Vec<u8>), JwtError> {
+    let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(Vec::new());
+    let accumulator = As::create_proof(&Default::default(), accumulators, &mut transcript, OsRng)
+        .map_err(|_| JwtError::ProofRejected)?;
+    Ok((accumulator, transcript.finalize()))
+}
+
+/// Limbs of an accumulator, as exposed by an aggregation circuit.
+pub(super) fn accumulator_limbs(accumulator: &KzgAccumulator<G1Affine, NativeLoader>) -> Vec<Fr> {
+    [accumulator.lhs.x, accumulator.lhs.y, accumulator.rhs.x, accumulator.rhs.y]
+        .map(fe_to_limbs::<_, _, LIMBS, BITS>)
+        .concat()
+}
+
+/// Loads the instances of `snark` and verifies it against `protocol` up to the final
+/// pairing.
+pub(super) fn succinct_verify<'a>(
+    svk: &Svk,
+    loader: &Rc<Halo2Loader<'a>>,
+    snark: &SnarkWitness,
+    protocol: &PlonkProtocol<G1Affine, Rc<Halo2Loader<'a>>>,
+) -> (Vec<Vec<LoadedScalar<'a>>>, Vec<LoadedAccumulator<'a>>) {
+    let instances = snark
+        .instances
+        .iter()
+        .map(|instances| {
+            instances
+                .iter()
+                .map(|instance| loader.assign_scalar(*instance))
+                .collect_vec()
+        })
+        .collect_vec();
+    let mut transcript = PoseidonTranscript::<Rc<Halo2Loader>, _>::new(loader, snark.proof());
+    let proof = PlonkSuccinctVerifier::read_proof(svk, protocol, &instances, &mut transcript).unwrap();
+    let accumulators = PlonkSuccinctVerifier::verify(svk, protocol, &instances, &proof).unwrap();
+
+    (instances, accumulators)
+}
+
+/// Folds accumulators in the circuit with the folding proof of [`accumulate_native`].
+pub(super) fn accumulate<'a>(
+    loader: &Rc<Halo2Loader<'a>>,
+    accumulators: &[LoadedAccumulator<'a>],
+    as_proof: Value<&'_ [u8]>,
+) -> LoadedAccumulator<'a> {
+    let mut transcript = PoseidonTranscript::<Rc<Halo2Loader>, _>::new(loader, as_proof);
+    let proof = As::read_proof(&Default::default(), accumulators, &mut transcript).unwrap();
+    As::verify(&Default::default(), accumulators, &proof).unwrap()
+}
+
+/// Assigns the limbs of an accumulator, to be exposed as instances.
+pub(super) fn assign_accumulator_limbs(
+    loader: &Rc<Halo2Loader<'_>>,
+    accumulator: &LoadedAccumulator<'_>,
+) -> Result<Vec<AssignedValue<Fr>>, Error> {
+    Ok([&accumulator.lhs, &accumulator.rhs]
+        .iter()
+        .map(|ec_point| {
+            loader
+                .ecc_chip()
+                .assign_ec_point_to_limbs(&mut loader.ctx_mut(), ec_point.assigned())
+        })
+        .collect::<Result<Vec<_>, Error>>()?
+        .into_iter()
+        .flatten()
+        .collect_vec())
+}
+
+/// Verifies every snark up to the final pairing, and returns the folded accumulator
+/// with the loaded instances of every snark.
+fn aggregate<'a>(
+    svk: &Svk,
+    loader: &Rc<Halo2Loader<'a>>,
+    snarks: &[SnarkWitness],
+    as_proof: Value<&'_ [u8]>,
+) -> (LoadedAccumulator<'a>, Vec<Vec<Vec<LoadedScalar<'a>>>>) {
+    let mut loaded_instances = Vec::with_capacity(snarks.len());
+    let mut accumulators = Vec::new();
+    for snark in snarks.iter() {
+        let protocol = snark.protocol.loaded(loader);
+        let (instances, snark_accumulators) = succinct_verify(svk, loader, snark, &protocol);
+        loaded_instances.push(instances);
+        accumulators.extend(snark_accumulators);
+    }
+
+    (accumulate(loader, &accumulators, as_proof), loaded_instances)
+}
+
+#[derive(Clone, Debug)]
+pub struct JwtAggregationConfig {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+}
+
+impl JwtAggregationConfig {
+    pub(super) fn configure(meta: &mut ConstraintSystem<Fr>) -> Self {
+        let main_gate_config = MainGate::<Fr>::configure(meta);
+        let range_config = RangeChip::<Fr>::configure(
+            meta,
+            &main_gate_config,
+            vec![BITS / LIMBS],
+            Rns::<Fq, Fr, LIMBS, BITS>::construct().overflow_lengths(),
+        );
+
+        Self {
+            main_gate_config,
+            range_config,
+        }
+    }
+
+    pub(super) fn main_gate(&self) -> MainGate<Fr> {
+        MainGate::new(self.main_gate_config.clone())
+    }
+
+    pub(super) fn range_chip(&self) -> RangeChip<Fr> {
+        RangeChip::new(self.range_config.clone())
+    }
+
+    pub(super) fn ecc_chip(&self) -> EccChip {
+        BaseFieldEccChip::new(EccConfig::new(
+            self.range_config.clone(),
+            self.main_gate_config.clone(),
+        ))
+    }
+}
+
+/// Aggregates SHPLONK snarks with Poseidon transcripts, exposing their instances.
+#[derive(Clone)]
+pub struct JwtAggregationCircuit {
+    svk: Svk,
+    snarks: Vec<SnarkWitness>,
+    instances: Vec<Fr>,
+    as_proof: Value<Vec<u8>>,
+}
+
+impl JwtAggregationCircuit {
+    /// Verifies `snarks` natively to build the witness of the folding, failing if any
+    /// of them is malformed.
+    pub fn new(params: &ParamsKZG<Bn256>, snarks: impl IntoIterator<Item = Snark>) -> Result<Self, JwtError> {
+        let svk: Svk = params.get_g()[0].into();
+        let snarks = snarks.into_iter().collect_vec();
+
+        let accumulators = snarks
+            .iter()
+            .map(|snark| succinct_verify_native(&svk, snark))
+            .flatten_ok()
+            .collect::<Result<Vec<_>, JwtError>>()?;
+        let (accumulator, as_proof) = accumulate_native(&accumulators)?;
+
+        let mut instances = accumulator_limbs(&accumulator);
+        for snark in snarks.iter() {
+            instances.extend(snark.instances.iter().flatten().copied());
+        }
+
+        Ok(Self {
+            svk,
+            snarks: snarks.into_iter().map_into().collect(),
+            instances,
+            as_proof: Value::known(as_proof),
+        })
+    }
+
+    /// The accumulator limbs.
+    pub fn accumulator(&self) -> &[Fr] {
+        &self.instances[..ACCUMULATOR_INSTANCES]
+    }
+
+    fn as_proof(&self) -> Value<&[u8]> {
+        self.as_proof.as_ref().map(Vec::as_slice)
+    }
+}
+
+impl Circuit<Fr> for JwtAggregationCircuit {
+    type Config = JwtAggregationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            svk: self.svk,
+            snarks: self.snarks.iter().map(SnarkWitness::without_witnesses).collect(),
+            instances: Vec::new(),
+            as_proof: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        JwtAggregationConfig::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        let main_gate = config.main_gate();
+        let range_chip = config.range_chip();
+        range_chip.load_table(&mut layouter)?;
+
+        let (accumulator_limbs, inner_instances) = layouter.assign_region(
+            || "aggregation",
+            |region| {
+                let ctx = RegionCtx::new(region, 0);
+                let loader = Halo2Loader::new(config.ecc_chip(), ctx);
+                let (accumulator, loaded_instances) =
+                    aggregate(&self.svk, &loader, &self.snarks, self.as_proof());
+
+                let accumulator_limbs = assign_accumulator_limbs(&loader, &accumulator)?;
+
+                // The same cells the inner proofs were verified against
+                let inner_instances = loaded_instances
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .map(|instance| instance.into_assigned())
+                    .collect_vec();
+
+                Ok((accumulator_limbs, inner_instances))
+            },
+        )?;
+
+        for (row, cell) in accumulator_limbs.into_iter().chain(inner_instances).enumerate() {
+            main_gate.expose_public(layouter.namespace(|| "public_instance"), cell, row)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CircuitExt<Fr> for JwtAggregationCircuit {
+    fn num_instance(&self) -> Vec<usize> {
+        let inner = self
+            .snarks
+            .iter()
+            .flat_map(|snark| snark.instances.iter().map(Vec::len))
+            .sum::<usize>();
+        vec![ACCUMULATOR_INSTANCES + inner]
+    }
+
+    fn instances(&self) -> Vec<Vec<Fr>> {
+        vec![self.instances.clone()]
+    }
+
+    fn accumulator_indices() -> Option<Vec<(usize, usize)>> {
+        Some((0..ACCUMULATOR_INSTANCES).map(|idx| (0, idx)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JwtAggregationCircuit, ACCUMULATOR_INSTANCES};
+    use crate::circuit::JwtCircuit;
+    use crate::error::JwtError;
+    use crate::precompute::PreComputed;
+    use crate::proof::{gen_pk, gen_snark, gen_srs, ProvingConfig};
+
+    use ff::Field;
+    use halo2_proofs::{dev::MockProver, poly::commitment::Params};
+    use halo2curves::bn256::Fr;
+    use snark_verifier_sdk::CircuitExt;
+
+    /// The aggregation circuit has `2^K` rows.
+    const K: u32 = 22;
+
+    #[test]
+    fn forwards_inner_instances() {
+        let circuits = ["123", "456"].map(|aud| {
+            let jwt = format!(
+                "{{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"{}\"}}",
+                aud
+            );
+            JwtCircuit::new(PreComputed::new(&jwt, "twitter|337834122"))
+        });
+        let inner_instances = circuits.iter().flat_map(|circuit| circuit.public_inputs::<Fr>()).collect::<Vec<_>>();
+
+        let params = gen_srs(K);
+        let mut params_app = params.clone();
+        params_app.downsize(circuits[0].dimensions().unwrap().min_k());
+        let pk = gen_pk(&params_app, &circuits[0]);
+        let snarks = circuits.map(|circuit| gen_snark(&params_app, &pk, circuit, None, &ProvingConfig::RECURSIVE).unwrap());
+
+        let circuit = JwtAggregationCircuit::new(&params, snarks.clone()).unwrap();
+        let instances = circuit.instances();
+        assert_eq!(circuit.num_instance(), vec![ACCUMULATOR_INSTANCES + inner_instances.len()]);
+        assert_eq!(instances[0][ACCUMULATOR_INSTANCES..], inner_instances[..]);
+        MockProver::run(K, &circuit, instances.clone()).unwrap().assert_satisfied();
+
+        // The forwarded instances are the ones the inner proofs were verified against
+        for idx in [0, 8, inner_instances.len() - 1] {
+            let mut tampered = instances.clone();
+            tampered[0][ACCUMULATOR_INSTANCES + idx] += Fr::ONE;
+            assert!(
+                MockProver::run(K, &circuit, tampered).unwrap().verify().is_err(),
+                "forwarded instance {} is not constrained",
+                idx
+            );
+        }
+
+        // A proof that does not parse is rejected before proving
+        let mut truncated = snarks[1].clone();
+        truncated.proof.truncate(truncated.proof.len() / 2);
+        assert!(matches!(
+            JwtAggregationCircuit::new(&params, [snarks[0].clone(), truncated]),
+            Err(JwtError::ProofRejected)
+        ));
+    }
+}
@@ -0,0 +1,644 @@
+//! A recursion circuit folding one `JwtCircuit` snark per step into a running chain.
+//!
+//! Every step verifies a new application snark and the snark of the previous step, and
+//! carries forward a commitment to the credential digest, a counter of the tokens folded
+//! so far and a commitment chaining their JWT digests. The previous step is a proof of
+//! this very circuit, so its preprocessed commitments are loaded as witnesses, and their
+//! digest is exposed and required to match the one exposed by the previous step. A
+//! verifier then compares the digest exposed by the last step with the one of the chain
+//! verifying key.
+//!
+//! Tokens are folded in strictly increasing order of their key, the first 224 bits of
+//! their JWT digest, and the key of the last one is carried forward. No token is thus
+//! folded twice, and the counter is a count of distinct tokens. A token whose key is
+//! below the last one can only start a new chain.
+//!
+//! The instances of a step are the accumulator limbs, followed by the preprocessed
+//! digest, the credential commitment, the counter, the tokens commitment and the key of
+//! the last token.
+
+use std::rc::Rc;
+
+use ff::{Field, PrimeField};
+use halo2_maingate::{AssignedCondition, MainGateInstructions, RangeInstructions, RegionCtx, Term};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{keygen_vk, Circuit, ConstraintSystem, Error, ProvingKey, VerifyingKey},
+    poly::{commitment::ParamsProver, kzg::commitment::ParamsKZG},
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine};
+use itertools::Itertools;
+use snark_verifier::{
+    loader::{native::NativeLoader, Loader},
+    pcs::kzg::KzgAccumulator,
+    system::halo2::{compile, Config},
+    util::{arithmetic::fe_to_fe, hash::Poseidon, transcript::TranscriptWrite},
+    verifier::plonk::PlonkProtocol,
+};
+use snark_verifier_sdk::{gen_pk, CircuitExt, Snark};
+
+use super::circuit::{
+    accumulate, accumulate_native, accumulator_limbs, assign_accumulator_limbs, succinct_verify,
+    succinct_verify_native, Halo2Loader, JwtAggregationConfig, LoadedAccumulator, PoseidonTranscript,
+    SnarkWitness, Svk, ACCUMULATOR_INSTANCES, RATE, R_F, R_P, T,
+};
+use crate::error::JwtError;
+use crate::proof::{gen_snark, ProvingConfig};
+
+const PREPROCESSED_DIGEST_ROW: usize = ACCUMULATOR_INSTANCES;
+const CREDENTIAL_COMMITMENT_ROW: usize = ACCUMULATOR_INSTANCES + 1;
+const COUNT_ROW: usize = ACCUMULATOR_INSTANCES + 2;
+const TOKENS_COMMITMENT_ROW: usize = ACCUMULATOR_INSTANCES + 3;
+const LAST_TOKEN_ROW: usize = ACCUMULATOR_INSTANCES + 4;
+const NUM_INSTANCE: usize = ACCUMULATOR_INSTANCES + 5;
+
+/// Instances of the JWT digest words within a `JwtCircuit` snark.
+const JWT_DIGEST: std::ops::Range<usize> = 0..8;
+/// Instances of the credential digest words within a `JwtCircuit` snark.
+const CREDENTIAL_DIGEST: std::ops::Range<usize> = 8..16;
+
+/// Leading JWT digest words making the key of a token, as many as fit in a scalar.
+const TOKEN_KEY_WORDS: usize = 7;
+const TOKEN_KEY_BITS: usize = 32 * TOKEN_KEY_WORDS;
+
+fn poseidon<L: Loader<G1Affine>>(loader: &L, inputs: &[L::LoadedScalar]) -> L::LoadedScalar {
+    let mut hasher = Poseidon::<Fr, L::LoadedScalar, T, RATE>::new(loader, R_F, R_P);
+    hasher.update(inputs);
+    hasher.squeeze()
+}
+
+/// Digest of the preprocessed commitments of `protocol`, identifying its verifying key.
+pub fn preprocessed_digest(protocol: &PlonkProtocol<G1Affine>) -> Fr {
+    let inputs = protocol
+        .preprocessed
+        .iter()
+        .flat_map(|preprocessed| [preprocessed.x, preprocessed.y])
+        .map(fe_to_fe)
+        .chain(protocol.transcript_initial_state)
+        .collect_vec();
+    poseidon(&NativeLoader, &inputs)
+}
+
+/// Commitment to the credential digest words of a `JwtCircuit` snark.
+pub fn credential_commitment(credential_digest: &[Fr]) -> Fr {
+    poseidon(&NativeLoader, credential_digest)
+}
+
+/// Commitment to the JWT digests of the tokens folded so far, `previous` being the
+/// commitment of the step before, or zero on the first one.
+pub fn tokens_commitment(previous: Fr, jwt_digest: &[Fr]) -> Fr {
+    let inputs = std::iter::once(previous).chain(jwt_digest.iter().copied()).collect_vec();
+    poseidon(&NativeLoader, &inputs)
+}
+
+/// Key ordering the tokens of a chain: the big-endian leading words of the JWT digest.
+pub fn token_key(jwt_digest: &[Fr]) -> Fr {
+    jwt_digest[..TOKEN_KEY_WORDS]
+        .iter()
+        .fold(Fr::ZERO, |key, word| key * Fr::from(1u64 << 32) + word)
+}
+
+/// Whether `key` is above `last`, both being below `2^TOKEN_KEY_BITS`.
+fn follows(key: &Fr, last: &Fr) -> bool {
+    key.to_repr().iter().rev().cmp(last.to_repr().iter().rev()).is_gt()
+}
+
+/// The state carried by a step of the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainState {
+    /// Commitment to the credential digest every folded token was checked against.
+    pub credential_commitment: Fr,
+    /// Number of distinct tokens folded into the chain.
+    pub count: u64,
+    /// Commitment to the JWT digests of the folded tokens, see [`tokens_commitment`].
+    pub tokens_commitment: Fr,
+    /// Key of the last folded token, see [`token_key`].
+    pub last_token: Fr,
+}
+
+/// Verifies a new application snark and the previous step of the chain.
+#[derive(Clone)]
+pub struct JwtChainCircuit {
+    svk: Svk,
+    default_accumulator: KzgAccumulator<G1Affine, NativeLoader>,
+    app: SnarkWitness,
+    previous: SnarkWitness,
+    count: Value<Fr>,
+    instances: Vec<Fr>,
+    as_proof: Value<Vec<u8>>,
+}
+
+impl JwtChainCircuit {
+    /// A step folding `app` into `previous`, ending with `count` tokens. The first step
+    /// has a `count` of one, and ignores `previous` but for the shape of its protocol.
+    /// Any other step fails unless `app` has the credential of `previous` and comes
+    /// after its last token.
+    pub fn new(params: &ParamsKZG<Bn256>, app: Snark, previous: Snark, count: u64) -> Result<Self, JwtError> {
+        if count > 1 {
+            let (app_instances, previous_instances) = step_instances(&app, &previous)?;
+            if previous_instances[CREDENTIAL_COMMITMENT_ROW] != credential_commitment(&app_instances[CREDENTIAL_DIGEST])
+                || previous_instances[COUNT_ROW] != Fr::from(count - 1)
+            {
+                return Err(JwtError::ChainMismatch);
+            }
+            if !follows(&token_key(&app_instances[JWT_DIGEST]), &previous_instances[LAST_TOKEN_ROW]) {
+                return Err(JwtError::TokenOutOfOrder);
+            }
+        }
+
+        Self::new_unchecked(params, app, previous, count)
+    }
+
+    /// [`JwtChainCircuit::new`] without checking that `app` may follow `previous`, which
+    /// the circuit checks again.
+    fn new_unchecked(params: &ParamsKZG<Bn256>, app: Snark, previous: Snark, count: u64) -> Result<Self, JwtError> {
+        let (app_instances, previous_instances) = step_instances(&app, &previous)?;
+        let previous_tokens = match count {
+            0 => return Err(JwtError::InvalidPublicInputs),
+            1 => Fr::ZERO,
+            _ => previous_instances[TOKENS_COMMITMENT_ROW],
+        };
+        let credential_commitment = credential_commitment(&app_instances[CREDENTIAL_DIGEST]);
+        let tokens_commitment = tokens_commitment(previous_tokens, &app_instances[JWT_DIGEST]);
+        let token_key = token_key(&app_instances[JWT_DIGEST]);
+
+        let svk: Svk = params.get_g()[0].into();
+        let default_accumulator = KzgAccumulator::new(params.get_g()[1], params.get_g()[0]);
+
+        let previous_accumulators = if count > 1 {
+            succinct_verify_native(&svk, &previous)?
+        } else {
+            let num_accumulator = 1 + previous.protocol.accumulator_indices.len();
+            vec![default_accumulator.clone(); num_accumulator]
+        };
+        let accumulators = succinct_verify_native(&svk, &app)?
+            .into_iter()
+            .chain(previous_accumulators)
+            .collect_vec();
+        let (accumulator, as_proof) = accumulate_native(&accumulators)?;
+
+        let mut instances = accumulator_limbs(&accumulator);
+        instances.extend([
+            preprocessed_digest(&previous.protocol),
+            credential_commitment,
+            Fr::from(count),
+            tokens_commitment,
+            token_key,
+        ]);
+
+        Ok(Self {
+            svk,
+            default_accumulator,
+            app: app.into(),
+            previous: previous.into(),
+            count: Value::known(Fr::from(count)),
+            instances,
+            as_proof: Value::known(as_proof),
+        })
+    }
+
+    fn as_proof(&self) -> Value<&[u8]> {
+        self.as_proof.as_ref().map(Vec::as_slice)
+    }
+
+    fn load_default_accumulator<'a>(&self, loader: &Rc<Halo2Loader<'a>>) -> Result<LoadedAccumulator<'a>, Error> {
+        let [lhs, rhs] = [self.default_accumulator.lhs, self.default_accumulator.rhs].map(|default| {
+            loader
+                .ecc_chip()
+                .assign_constant(&mut loader.ctx_mut(), default)
+                .map(|assigned| loader.ec_point_from_assigned(assigned))
+        });
+        Ok(KzgAccumulator::new(lhs?, rhs?))
+    }
+}
+
+/// The instances of an application snark and of a chain step, if they have the shape
+/// of the ones of `JwtCircuit` and [`JwtChainCircuit`].
+fn step_instances<'a>(app: &'a Snark, previous: &'a Snark) -> Result<(&'a [Fr], &'a [Fr]), JwtError> {
+    let app_instances = match app.instances.as_slice() {
+        [instances] if instances.len() >= CREDENTIAL_DIGEST.end => instances,
+        _ => return Err(JwtError::InvalidPublicInputs),
+    };
+    let previous_instances = match previous.instances.as_slice() {
+        [instances] if instances.len() == NUM_INSTANCE => instances,
+        _ => return Err(JwtError::ChainMismatch),
+    };
+    Ok((app_instances, previous_instances))
+}
+
+/// `lhs` if `condition` is set, `rhs` otherwise.
+fn select_accumulator<'a>(
+    loader: &Rc<Halo2Loader<'a>>,
+    condition: &AssignedCondition<Fr>,
+    lhs: &LoadedAccumulator<'a>,
+    rhs: &LoadedAccumulator<'a>,
+) -> Result<LoadedAccumulator<'a>, Error> {
+    let [lhs, rhs] = [(&lhs.lhs, &rhs.lhs), (&lhs.rhs, &rhs.rhs)].map(|(lhs, rhs)| {
+        loader
+            .ecc_chip()
+            .select(&mut loader.ctx_mut(), condition, &lhs.assigned(), &rhs.assigned())
+            .map(|assigned| loader.ec_point_from_assigned(assigned))
+    });
+    Ok(KzgAccumulator::new(lhs?, rhs?))
+}
+
+impl Circuit<Fr> for JwtChainCircuit {
+    type Config = JwtAggregationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            svk: self.svk,
+            default_accumulator: self.default_accumulator.clone(),
+            app: self.app.without_witnesses(),
+            previous: self.previous.without_witnesses(),
+            count: Value::unknown(),
+            instances: Vec::new(),
+            as_proof: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        JwtAggregationConfig::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        let main_gate = config.main_gate();
+        let range_chip = config.range_chip();
+        range_chip.load_table(&mut layouter)?;
+
+        let instances = layouter.assign_region(
+            || "recursion",
+            |region| {
+                let ctx = RegionCtx::new(region, 0);
+                let loader = Halo2Loader::new(config.ecc_chip(), ctx);
+
+                // The application key is fixed, the previous one is whatever hashes to
+                // the exposed digest
+                let app_protocol = self.app.protocol.loaded(&loader);
+                let (app_instances, app_accumulators) =
+                    succinct_verify(&self.svk, &loader, &self.app, &app_protocol);
+
+                let previous_protocol = self.previous.protocol.loaded_preprocessed_as_witness(&loader);
+                let preprocessed_digest = {
+                    let inputs = previous_protocol
+                        .preprocessed
+                        .iter()
+                        .flat_map(|preprocessed| {
+                            let assigned = preprocessed.assigned();
+                            [assigned.x(), assigned.y()]
+                                .map(|coordinate| loader.scalar_from_assigned(coordinate.native().clone()))
+                        })
+                        .chain(previous_protocol.transcript_initial_state.clone())
+                        .collect_vec();
+                    poseidon(&loader, &inputs)
+                };
+                let (previous_instances, previous_accumulators) =
+                    succinct_verify(&self.svk, &loader, &self.previous, &previous_protocol);
+
+                let credential_commitment = poseidon(&loader, &app_instances[0][CREDENTIAL_DIGEST]);
+                let count = loader.assign_scalar(self.count);
+
+                let [preprocessed_digest, credential_commitment, count] =
+                    [preprocessed_digest, credential_commitment, count].map(|scalar| scalar.into_assigned());
+                let [previous_digest, previous_commitment, previous_count, previous_tokens, previous_key] = [
+                    PREPROCESSED_DIGEST_ROW,
+                    CREDENTIAL_COMMITMENT_ROW,
+                    COUNT_ROW,
+                    TOKENS_COMMITMENT_ROW,
+                    LAST_TOKEN_ROW,
+                ]
+                .map(|row| previous_instances[0][row].clone().into_assigned());
+                let jwt_digest = app_instances[0][JWT_DIGEST]
+                    .iter()
+                    .map(|word| word.clone().into_assigned())
+                    .collect_vec();
+
+                let (is_first, chained_tokens, token_key) = {
+                    let ctx = &mut *loader.ctx_mut();
+                    let count_minus_one = main_gate.add_constant(ctx, &count, -Fr::ONE)?;
+                    let is_first = main_gate.is_zero(ctx, &count_minus_one)?;
+                    let expected_count = main_gate.add_constant(ctx, &previous_count, Fr::ONE)?;
+
+                    // On the first step there is no previous step to agree with
+                    for (current, previous) in [
+                        (&preprocessed_digest, &previous_digest),
+                        (&credential_commitment, &previous_commitment),
+                        (&count, &expected_count),
+                    ] {
+                        let selected = main_gate.select(ctx, previous, current, &is_first)?;
+                        main_gate.assert_equal(ctx, &selected, previous)?;
+                    }
+
+                    // The digest words are range checked by the application circuit, so
+                    // the key is below 2^TOKEN_KEY_BITS
+                    let terms = jwt_digest[..TOKEN_KEY_WORDS]
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, word)| {
+                            Term::Assigned(word, Fr::from(1u64 << 32).pow([(TOKEN_KEY_WORDS - 1 - idx) as u64]))
+                        })
+                        .collect_vec();
+                    let token_key = main_gate.compose(ctx, &terms, Fr::ZERO)?;
+
+                    // The key is at least one above the previous one, anything below
+                    // wraps around the field and does not fit in TOKEN_KEY_BITS
+                    let zero = main_gate.assign_constant(ctx, Fr::ZERO)?;
+                    let floor = main_gate.add_constant(ctx, &previous_key, Fr::ONE)?;
+                    let floor = main_gate.select(ctx, &zero, &floor, &is_first)?;
+                    let gap = main_gate.sub(ctx, &token_key, &floor)?;
+                    main_gate.to_bits(ctx, &gap, TOKEN_KEY_BITS)?;
+
+                    let chained_tokens = main_gate.select(ctx, &zero, &previous_tokens, &is_first)?;
+                    (is_first, chained_tokens, token_key)
+                };
+
+                let tokens_commitment = {
+                    let inputs = std::iter::once(loader.scalar_from_assigned(chained_tokens))
+                        .chain(app_instances[0][JWT_DIGEST].iter().cloned())
+                        .collect_vec();
+                    poseidon(&loader, &inputs).into_assigned()
+                };
+
+                let default_accumulator = self.load_default_accumulator(&loader)?;
+                let previous_accumulators = previous_accumulators
+                    .iter()
+                    .map(|previous| select_accumulator(&loader, &is_first, &default_accumulator, previous))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let accumulator = accumulate(
+                    &loader,
+                    &[app_accumulators, previous_accumulators].concat(),
+                    self.as_proof(),
+                );
+
+                let mut instances = assign_accumulator_limbs(&loader, &accumulator)?;
+                instances.extend([preprocessed_digest, credential_commitment, count, tokens_commitment, token_key]);
+                Ok(instances)
+            },
+        )?;
+
+        for (row, cell) in instances.into_iter().enumerate() {
+            main_gate.expose_public(layouter.namespace(|| "public_instance"), cell, row)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CircuitExt<Fr> for JwtChainCircuit {
+    fn num_instance(&self) -> Vec<usize> {
+        vec![NUM_INSTANCE]
+    }
+
+    fn instances(&self) -> Vec<Vec<Fr>> {
+        vec![self.instances.clone()]
+    }
+
+    fn accumulator_indices() -> Option<Vec<(usize, usize)>> {
+        Some((0..ACCUMULATOR_INSTANCES).map(|idx| (0, idx)).collect())
+    }
+}
+
+/// Has the configuration of [`JwtChainCircuit`] and nothing else, for a verifying key
+/// with the shape of the chain one before the chain key exists.
+#[derive(Clone, Default)]
+struct ChainShape;
+
+impl Circuit<Fr> for ChainShape {
+    type Config = JwtAggregationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        JwtAggregationConfig::configure(meta)
+    }
+
+    fn synthesize(&self, _: Self::Config, _: impl Layouter<Fr>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A snark of a chain step that only parses, standing in for the previous step of the
+/// first one.
+fn dummy_snark(params: &ParamsKZG<Bn256>, vk: &VerifyingKey<G1Affine>) -> Result<Snark, JwtError> {
+    let protocol = compile(
+        params,
+        vk,
+        Config::kzg()
+            .with_num_instance(vec![NUM_INSTANCE])
+            .with_accumulator_indices(JwtChainCircuit::accumulator_indices()),
+    );
+    let instances = vec![vec![Fr::ZERO; NUM_INSTANCE]];
+
+    // Witness and quotient commitments, evaluations, then the two commitments of a
+    // SHPLONK opening
+    let num_commitment = protocol.num_witness.iter().sum::<usize>() + protocol.quotient.num_chunk() + 2;
+    let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(Vec::new());
+    for _ in 0..num_commitment {
+        transcript.write_ec_point(params.get_g()[0]).map_err(transcript_error)?;
+    }
+    for _ in 0..protocol.evaluations.len() {
+        transcript.write_scalar(Fr::ZERO).map_err(transcript_error)?;
+    }
+
+    Ok(Snark::new(protocol, instances, transcript.finalize()))
+}
+
+fn transcript_error(e: snark_verifier::Error) -> JwtError {
+    JwtError::Prover(Error::Transcript(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        format!("{:?}", e),
+    )))
+}
+
+/// Folds application snarks one at a time into a chain with a fixed proving key.
+pub struct JwtChain {
+    params: ParamsKZG<Bn256>,
+    pk: ProvingKey<G1Affine>,
+    dummy: Snark,
+    digest: Fr,
+}
+
+impl JwtChain {
+    /// Generates the chain proving key for application snarks of the same key as `app`.
+    pub fn new(params: ParamsKZG<Bn256>, app: &Snark) -> Result<Self, JwtError> {
+        let shape = keygen_vk(&params, &ChainShape)?;
+        let circuit = JwtChainCircuit::new(&params, app.clone(), dummy_snark(&params, &shape)?, 1)?;
+        let pk = gen_pk(&params, &circuit, None);
+
+        let dummy = dummy_snark(&params, pk.get_vk())?;
+        let digest = preprocessed_digest(&dummy.protocol);
+
+        Ok(Self { params, pk, dummy, digest })
+    }
+
+    pub fn pk(&self) -> &ProvingKey<G1Affine> {
+        &self.pk
+    }
+
+    /// Folds `app` into the chain ending at `previous`, or starts a new chain.
+    pub fn fold(&self, app: Snark, previous: Option<&Snark>) -> Result<Snark, JwtError> {
+        let (previous, count) = match previous {
+            Some(previous) => (previous.clone(), self.state(previous)?.count + 1),
+            None => (self.dummy.clone(), 1),
+        };
+
+        let circuit = JwtChainCircuit::new(&self.params, app, previous, count)?;
+        Ok(gen_snark(&self.params, &self.pk, circuit, None, &ProvingConfig::RECURSIVE)?)
+    }
+
+    /// Decodes the state of a step, checking it was proven with the chain key. This does
+    /// not verify the proof.
+    pub fn state(&self, snark: &Snark) -> Result<ChainState, JwtError> {
+        let instances = match snark.instances.as_slice() {
+            [instances] if instances.len() == NUM_INSTANCE => instances,
+            _ => return Err(JwtError::InvalidPublicInputs),
+        };
+        if instances[PREPROCESSED_DIGEST_ROW] != self.digest {
+            return Err(JwtError::ChainMismatch);
+        }
+
+        let count = instances[COUNT_ROW].to_repr();
+        if count[8..].iter().any(|byte| *byte != 0) {
+            return Err(JwtError::InvalidPublicInputs);
+        }
+
+        Ok(ChainState {
+            credential_commitment: instances[CREDENTIAL_COMMITMENT_ROW],
+            count: u64::from_le_bytes(count[..8].try_into().unwrap()),
+            tokens_commitment: instances[TOKENS_COMMITMENT_ROW],
+            last_token: instances[LAST_TOKEN_ROW],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        dummy_snark, token_key, tokens_commitment, ChainShape, JwtChainCircuit, COUNT_ROW,
+        CREDENTIAL_COMMITMENT_ROW, JWT_DIGEST, LAST_TOKEN_ROW, PREPROCESSED_DIGEST_ROW, TOKENS_COMMITMENT_ROW,
+    };
+    use crate::circuit::JwtCircuit;
+    use crate::error::JwtError;
+    use crate::precompute::PreComputed;
+    use crate::proof::{gen_pk, gen_snark, gen_srs, ProvingConfig};
+
+    use ff::Field;
+    use halo2_proofs::{
+        dev::MockProver,
+        plonk::keygen_vk,
+        poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    };
+    use halo2curves::bn256::{Bn256, Fr};
+    use snark_verifier_sdk::{CircuitExt, Snark};
+
+    /// The chain circuit has `2^K` rows.
+    const K: u32 = 22;
+
+    /// Application snarks of three tokens sharing a verifying key, the first two for the
+    /// same credential and sorted by key, the last one for another credential.
+    fn setup() -> (ParamsKZG<Bn256>, [Snark; 3]) {
+        let tokens = [
+            ("{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\"}", "twitter|337834122"),
+            ("{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"456\"}", "twitter|337834122"),
+            ("{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834123\",\"aud\":\"123\"}", "twitter|337834123"),
+        ]
+        .map(|(jwt, credential)| JwtCircuit::new(PreComputed::new(jwt, credential)));
+
+        let params = gen_srs(K);
+        let mut params_app = params.clone();
+        params_app.downsize(tokens[0].dimensions().unwrap().min_k());
+        let pk = gen_pk(&params_app, &tokens[0]);
+        let [mut first, mut second, other] =
+            tokens.map(|circuit| gen_snark(&params_app, &pk, circuit, None, &ProvingConfig::RECURSIVE).unwrap());
+        if !super::follows(&key(&second), &key(&first)) {
+            std::mem::swap(&mut first, &mut second);
+        }
+
+        (params, [first, second, other])
+    }
+
+    fn key(app: &Snark) -> Fr {
+        token_key(&app.instances[0][JWT_DIGEST])
+    }
+
+    /// The first step of a chain, and a snark standing in for its proof.
+    fn first_step(params: &ParamsKZG<Bn256>, app: &Snark) -> (JwtChainCircuit, Snark) {
+        let shape = keygen_vk(params, &ChainShape).unwrap();
+        let dummy = dummy_snark(params, &shape).unwrap();
+        let circuit = JwtChainCircuit::new(params, app.clone(), dummy.clone(), 1).unwrap();
+
+        // The proof is not checked by the mock prover, only its instances matter
+        let step = Snark::new(dummy.protocol, circuit.instances(), dummy.proof);
+        (circuit, step)
+    }
+
+    fn satisfied(circuit: &JwtChainCircuit) -> bool {
+        MockProver::run(K, circuit, circuit.instances()).unwrap().verify().is_ok()
+    }
+
+    #[test]
+    fn chain() {
+        let (params, [first, second, other]) = setup();
+
+        let (circuit, step) = first_step(&params, &first);
+        assert!(satisfied(&circuit));
+        assert_eq!(step.instances[0][COUNT_ROW], Fr::from(1));
+        assert_eq!(
+            step.instances[0][TOKENS_COMMITMENT_ROW],
+            tokens_commitment(Fr::ZERO, &first.instances[0][JWT_DIGEST])
+        );
+        assert_eq!(step.instances[0][LAST_TOKEN_ROW], key(&first));
+
+        let circuit = JwtChainCircuit::new(&params, second.clone(), step.clone(), 2).unwrap();
+        assert!(satisfied(&circuit));
+        let instances = &circuit.instances()[0];
+        assert_eq!(instances[COUNT_ROW], Fr::from(2));
+        assert_eq!(instances[CREDENTIAL_COMMITMENT_ROW], step.instances[0][CREDENTIAL_COMMITMENT_ROW]);
+        assert_eq!(
+            instances[TOKENS_COMMITMENT_ROW],
+            tokens_commitment(step.instances[0][TOKENS_COMMITMENT_ROW], &second.instances[0][JWT_DIGEST])
+        );
+        assert_eq!(instances[LAST_TOKEN_ROW], key(&second));
+
+        // A token is not folded twice, nor one below the last
+        let (_, last) = first_step(&params, &second);
+        for app in [&first, &second] {
+            assert!(matches!(
+                JwtChainCircuit::new(&params, app.clone(), last.clone(), 2),
+                Err(JwtError::TokenOutOfOrder)
+            ));
+        }
+        assert!(matches!(
+            JwtChainCircuit::new(&params, other, step.clone(), 2),
+            Err(JwtError::ChainMismatch)
+        ));
+        assert!(matches!(
+            JwtChainCircuit::new(&params, second, step, 3),
+            Err(JwtError::ChainMismatch)
+        ));
+    }
+
+    #[test]
+    fn previous_step_mismatch_is_rejected() {
+        let (params, [first, second, _]) = setup();
+        let (_, step) = first_step(&params, &first);
+
+        // Another credential, another chain key, a count not one below, a previous
+        // token at or above the new one
+        for (row, value) in [
+            (CREDENTIAL_COMMITMENT_ROW, Fr::from(1)),
+            (PREPROCESSED_DIGEST_ROW, Fr::from(1)),
+            (COUNT_ROW, Fr::from(2)),
+            (LAST_TOKEN_ROW, key(&second)),
+        ] {
+            let mut previous = step.clone();
+            previous.instances[0][row] = value;
+            let circuit = JwtChainCircuit::new_unchecked(&params, second.clone(), previous, 2).unwrap();
+            assert!(!satisfied(&circuit), "previous instance {} is not checked", row);
+        }
+    }
+}
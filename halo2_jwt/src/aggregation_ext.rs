@@ -7,7 +7,7 @@ impl CircuitExt<Fr> for JwtCircuit {
     
     // number of public inputs
     fn num_instance(&self) -> Vec<usize> {
-        vec![18]
+        vec![self.public_inputs::<Fr>().len()]
     }
 
     // the public inputs
@@ -0,0 +1,151 @@
+//! Proving many tokens of the same circuit params with a single proving key.
+
+use std::{
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use halo2_proofs::plonk::ProvingKey;
+
+use crate::bundle::JwtProof;
+use crate::circuit::{JwtCircuit, JwtCircuitParams};
+use crate::error::JwtError;
+use crate::precompute::PreComputed;
+use crate::proof::{prove_jwt, Backend, ProvingConfig};
+
+/// The outcome of proving one token of a batch.
+#[derive(Debug)]
+pub struct BatchResult {
+    /// Position of the token in the batch.
+    pub index: usize,
+    /// Time spent on this token, including the check of its proof.
+    pub elapsed: Duration,
+    pub proof: Result<JwtProof, JwtError>,
+}
+
+/// Proves batches of tokens across threads, sharing one proving key.
+pub struct BatchProver<'a, B: Backend> {
+    params: &'a B::Params,
+    pk: &'a ProvingKey<B::Affine>,
+    circuit_params: JwtCircuitParams,
+    config: ProvingConfig,
+    threads: usize,
+    _marker: PhantomData<fn() -> B>,
+}
+
+impl<'a, B: Backend> BatchProver<'a, B>
+where
+    B::Params: Sync,
+    B::Affine: Sync,
+{
+    /// A prover for tokens of `circuit_params`, with one thread per available core.
+    pub fn new(
+        params: &'a B::Params,
+        pk: &'a ProvingKey<B::Affine>,
+        circuit_params: JwtCircuitParams,
+        config: ProvingConfig,
+    ) -> Self {
+        let threads = thread::available_parallelism().map(usize::from).unwrap_or(1);
+        Self {
+            params,
+            pk,
+            circuit_params,
+            config,
+            threads,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_threads(self, threads: usize) -> Self {
+        Self { threads: threads.max(1), ..self }
+    }
+
+    /// Proves every token. A failure only affects the result of its own token, and
+    /// results are returned in the order of `inputs`.
+    pub fn prove(&self, inputs: Vec<PreComputed>) -> Vec<BatchResult> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(inputs.len()));
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(inputs.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(precomputed) = inputs.get(index) else {
+                        break;
+                    };
+
+                    let start = Instant::now();
+                    let proof = self.prove_one(precomputed.clone());
+                    let elapsed = start.elapsed();
+                    match &proof {
+                        Ok(_) => log::info!("[Batch] Proof {:?} generated in {:?}", index, elapsed),
+                        Err(e) => log::warn!("[Batch] Proof {:?} failed in {:?}: {}", index, elapsed, e),
+                    }
+
+                    results.lock().unwrap().push(BatchResult { index, elapsed, proof });
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|result| result.index);
+        results
+    }
+
+    fn prove_one(&self, precomputed: PreComputed) -> Result<JwtProof, JwtError> {
+        let circuit = JwtCircuit::new(precomputed);
+        if circuit.params(self.circuit_params.k) != self.circuit_params {
+            return Err(JwtError::ParamsMismatch);
+        }
+
+        let proof = prove_jwt::<B>(self.params, self.pk, circuit.clone(), &self.config)?;
+        let bundle = JwtProof::new::<B>(self.circuit_params.k, &self.config, &circuit, self.pk.get_vk(), proof);
+
+        // The prover does not check the witness, so an invalid one shows up here
+        let instances = bundle.instances();
+        if !B::verify(self.params, self.pk.get_vk(), &instances, &bundle.proof, &self.config) {
+            return Err(JwtError::ProofRejected);
+        }
+
+        Ok(bundle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BatchProver;
+    use crate::circuit::JwtCircuit;
+    use crate::error::JwtError;
+    use crate::precompute::PreComputed;
+    use crate::proof::{Backend, Kzg, ProvingConfig};
+
+    #[test]
+    fn batch_proves_each_token() {
+        let k = 17;
+        let jwt = |sub: &str| format!("{{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"{}\",\"aud\":\"123\"}}", sub);
+        let inputs = vec![
+            PreComputed::new(&jwt("twitter|337834122"), "twitter|337834122"),
+            // A longer token does not fit the keys of the others
+            PreComputed::new(&jwt("twitter|3378341220"), "twitter|3378341220"),
+            PreComputed::new(&jwt("twitter|123456789"), "twitter|123456789"),
+        ];
+
+        let circuit = JwtCircuit::new(inputs[0].clone());
+        let params = Kzg::setup(k);
+        let pk = Kzg::keygen(&params, &circuit).unwrap();
+
+        let results = BatchProver::<Kzg>::new(&params, &pk, circuit.params(k), ProvingConfig::NATIVE)
+            .with_threads(2)
+            .prove(inputs);
+
+        assert_eq!(results.iter().map(|result| result.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(results[0].proof.is_ok());
+        assert!(matches!(results[1].proof, Err(JwtError::ParamsMismatch)));
+        assert!(results[2].proof.is_ok());
+    }
+}
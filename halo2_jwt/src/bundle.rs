@@ -0,0 +1,413 @@
+//! A versioned, self-describing bundle of a `JwtCircuit` proof.
+//!
+//! A [`JwtProof`] carries the proof bytes together with what a verifier needs to check
+//! it was produced for the expected circuit and key, and the public inputs decoded into
+//! named fields. It has two encodings:
+//!
+//! - JSON, through serde. Byte strings are lowercase hex.
+//! - A compact binary layout, with every integer big-endian:
+//!
+//! | field                  | size            |
+//! |------------------------|-----------------|
+//! | magic `JWTP`           | 4               |
+//! | version                | 2               |
+//! | circuit params hash    | 32              |
+//! | vk hash                | 32              |
+//! | JWT digest             | 32              |
+//! | credential digest      | 32              |
+//! | modulus length `m`     | 4               |
+//! | modulus                | `m`, 0 or 256   |
+//! | proof length `p`       | 4               |
+//! | proof                  | `p`             |
+//!
+//! A modulus length of zero means no signature is verified by the proof. Otherwise the
+//! modulus takes its full 256 bytes, leading zeros included, so it decodes back into
+//! the same limbs.
+//!
+//! Both decoders reject bundles of any version other than [`FORMAT_VERSION`].
+
+use std::{convert::TryInto, fmt};
+
+use ff::PrimeField;
+use halo2_proofs::plonk::VerifyingKey;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::circuit::{JwtCircuit, JwtCircuitParams};
+use crate::proof::{Backend, ProvingConfig};
+use crate::rsa::{limbs_as_field, LIMB_BITS, MODULUS_BITS, NUMBER_OF_LIMBS};
+
+/// Version of the bundle layout written by this crate.
+pub const FORMAT_VERSION: u16 = 1;
+
+const MAGIC: &[u8; 4] = b"JWTP";
+
+/// Number of public inputs before the modulus limbs.
+const JWT_INSTANCES: usize = 16;
+
+/// Width of an encoded modulus.
+const MODULUS_BYTES: usize = MODULUS_BITS / 8;
+
+#[derive(Debug)]
+pub enum BundleError {
+    /// The bundle was written by an unknown version of the format.
+    UnsupportedVersion(u16),
+    /// The binary encoding is truncated or otherwise malformed.
+    Malformed(&'static str),
+    /// The public inputs do not decode into JWT public inputs.
+    InvalidPublicInputs,
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::UnsupportedVersion(version) => {
+                write!(f, "unsupported proof bundle version {}", version)
+            }
+            BundleError::Malformed(reason) => write!(f, "malformed proof bundle: {}", reason),
+            BundleError::InvalidPublicInputs => write!(f, "invalid public inputs"),
+            BundleError::Json(e) => write!(f, "invalid proof bundle json: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl From<serde_json::Error> for BundleError {
+    fn from(e: serde_json::Error) -> Self {
+        BundleError::Json(e)
+    }
+}
+
+/// The public inputs of a `JwtCircuit`, by name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwtPublicInputs {
+    #[serde(with = "hex")]
+    pub jwt_digest: [u8; 32],
+    /// SHA-256 digest of the credential alone, wherever it sits in the JWT.
+    #[serde(with = "hex")]
+    pub credential_digest: [u8; 32],
+    /// Big-endian `PS256` modulus of exactly 256 bytes, if the proof verifies a
+    /// signature.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "hex_option")]
+    pub modulus: Option<Vec<u8>>,
+}
+
+impl JwtPublicInputs {
+    /// Decodes the instance column of a `JwtCircuit`.
+    pub fn from_instances<F: PrimeField>(instances: &[F]) -> Result<Self, BundleError> {
+        let values = instances
+            .iter()
+            .map(|value| {
+                let repr = value.to_repr();
+                let (low, high) = repr.as_ref().split_at(8);
+                if high.iter().any(|byte| *byte != 0) {
+                    return Err(BundleError::InvalidPublicInputs);
+                }
+                Ok(u64::from_le_bytes(low.try_into().unwrap()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let modulus = match values.len() {
+            JWT_INSTANCES => None,
+            len if len == JWT_INSTANCES + NUMBER_OF_LIMBS => {
+                let mut modulus = vec![0u8; MODULUS_BYTES];
+                let limbs = values[JWT_INSTANCES..].iter().rev();
+                for (bytes, limb) in modulus.chunks_exact_mut(LIMB_BITS / 8).zip(limbs) {
+                    bytes.copy_from_slice(&limb.to_be_bytes());
+                }
+                Some(modulus)
+            }
+            _ => return Err(BundleError::InvalidPublicInputs),
+        };
+
+        let digest = |words: &[u64]| -> Result<[u8; 32], BundleError> {
+            let mut result = [0u8; 32];
+            for (bytes, word) in result.chunks_exact_mut(4).zip(words) {
+                let word: u32 = (*word).try_into().map_err(|_| BundleError::InvalidPublicInputs)?;
+                bytes.copy_from_slice(&word.to_be_bytes());
+            }
+            Ok(result)
+        };
+
+        Ok(Self {
+            jwt_digest: digest(&values[0..8])?,
+            credential_digest: digest(&values[8..16])?,
+            modulus,
+        })
+    }
+
+    /// Encodes back into the instance column of a `JwtCircuit`.
+    pub fn to_instances<F: PrimeField>(&self) -> Vec<F> {
+        let mut result = Vec::with_capacity(JWT_INSTANCES);
+        for digest in [&self.jwt_digest, &self.credential_digest] {
+            for word in digest.chunks_exact(4) {
+                result.push(F::from(u32::from_be_bytes(word.try_into().unwrap()) as u64));
+            }
+        }
+
+        if let Some(modulus) = &self.modulus {
+            result.extend(limbs_as_field::<F>(&BigUint::from_bytes_be(modulus)));
+        }
+
+        result
+    }
+}
+
+/// A proof of a `JwtCircuit`, with enough context to check what it was produced for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwtProof {
+    pub version: u16,
+    /// See [`circuit_params_hash`].
+    #[serde(with = "hex")]
+    pub circuit_params_hash: [u8; 32],
+    /// See [`vk_hash`].
+    #[serde(with = "hex")]
+    pub vk_hash: [u8; 32],
+    pub public_inputs: JwtPublicInputs,
+    #[serde(with = "hex")]
+    pub proof: Vec<u8>,
+}
+
+impl JwtProof {
+    /// Bundles a proof of `circuit` produced with `B` at degree `k`.
+    pub fn new<B: Backend>(
+        k: u32,
+        config: &ProvingConfig,
+        circuit: &JwtCircuit,
+        vk: &VerifyingKey<B::Affine>,
+        proof: Vec<u8>,
+    ) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            circuit_params_hash: circuit_params_hash::<B>(&circuit.params(k), config),
+            vk_hash: vk_hash(vk),
+            public_inputs: JwtPublicInputs::from_instances(&circuit.public_inputs::<B::Scalar>())
+                .expect("JwtCircuit public inputs to decode"),
+            proof,
+        }
+    }
+
+    /// The instances to verify the proof against.
+    pub fn instances<F: PrimeField>(&self) -> Vec<Vec<F>> {
+        vec![self.public_inputs.to_instances()]
+    }
+
+    pub fn to_json(&self) -> Result<String, BundleError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, BundleError> {
+        let result: Self = serde_json::from_str(json)?;
+        if result.version != FORMAT_VERSION {
+            return Err(BundleError::UnsupportedVersion(result.version));
+        }
+        if result.public_inputs.modulus.as_ref().map_or(false, |modulus| modulus.len() != MODULUS_BYTES) {
+            return Err(BundleError::Malformed("modulus length"));
+        }
+
+        Ok(result)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let modulus = self.public_inputs.modulus.as_deref().unwrap_or_default();
+
+        let mut result = Vec::with_capacity(172 + modulus.len() + self.proof.len());
+        result.extend_from_slice(MAGIC);
+        result.extend_from_slice(&self.version.to_be_bytes());
+        result.extend_from_slice(&self.circuit_params_hash);
+        result.extend_from_slice(&self.vk_hash);
+        result.extend_from_slice(&self.public_inputs.jwt_digest);
+        result.extend_from_slice(&self.public_inputs.credential_digest);
+        result.extend_from_slice(&(modulus.len() as u32).to_be_bytes());
+        result.extend_from_slice(modulus);
+        result.extend_from_slice(&(self.proof.len() as u32).to_be_bytes());
+        result.extend_from_slice(&self.proof);
+
+        result
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BundleError> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err(BundleError::Malformed("bad magic"));
+        }
+
+        let version = u16::from_be_bytes(reader.array()?);
+        if version != FORMAT_VERSION {
+            return Err(BundleError::UnsupportedVersion(version));
+        }
+
+        let circuit_params_hash = reader.array()?;
+        let vk_hash = reader.array()?;
+        let jwt_digest = reader.array()?;
+        let credential_digest = reader.array()?;
+        let modulus = reader.take_prefixed()?;
+        if !modulus.is_empty() && modulus.len() != MODULUS_BYTES {
+            return Err(BundleError::Malformed("modulus length"));
+        }
+        let proof = reader.take_prefixed()?.to_vec();
+        if !reader.0.is_empty() {
+            return Err(BundleError::Malformed("trailing bytes"));
+        }
+
+        Ok(Self {
+            version,
+            circuit_params_hash,
+            vk_hash,
+            public_inputs: JwtPublicInputs {
+                jwt_digest,
+                credential_digest,
+                modulus: (!modulus.is_empty()).then(|| modulus.to_vec()),
+            },
+            proof,
+        })
+    }
+}
+
+/// Hashes what fixes the circuit a proof is for: the backend, the circuit params and
+/// the proving config.
+pub fn circuit_params_hash<B: Backend>(params: &JwtCircuitParams, config: &ProvingConfig) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(B::NAME.as_bytes());
+    hasher.update([0]);
+    hasher.update(params.to_bytes());
+    hasher.update([config.multi_open as u8, config.transcript as u8]);
+    hasher.finalize().into()
+}
+
+/// The hash of a verifying key, as absorbed by the transcript.
+pub fn vk_hash<C: halo2curves::CurveAffine>(vk: &VerifyingKey<C>) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    let repr = vk.transcript_repr().to_repr();
+    result.copy_from_slice(repr.as_ref());
+    result
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BundleError> {
+        if self.0.len() < len {
+            return Err(BundleError::Malformed("truncated"));
+        }
+        let (result, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(result)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BundleError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn take_prefixed(&mut self) -> Result<&'a [u8], BundleError> {
+        let len = u32::from_be_bytes(self.array()?);
+        self.take(len as usize)
+    }
+}
+
+mod hex_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_str(&hex::encode(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| hex::decode(value).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BundleError, JwtProof, JwtPublicInputs, FORMAT_VERSION};
+    use halo2curves::bn256::Fr;
+
+    fn bundle(modulus: Option<Vec<u8>>) -> JwtProof {
+        JwtProof {
+            version: FORMAT_VERSION,
+            circuit_params_hash: [1; 32],
+            vk_hash: [2; 32],
+            public_inputs: JwtPublicInputs {
+                jwt_digest: [3; 32],
+                credential_digest: [4; 32],
+                modulus,
+            },
+            proof: vec![5, 6, 7],
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        for bundle in [bundle(None), bundle(Some(vec![0xc0; 256]))] {
+            let json = bundle.to_json().unwrap();
+            assert_eq!(JwtProof::from_json(&json).unwrap(), bundle);
+        }
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        for bundle in [bundle(None), bundle(Some(vec![0xc0; 256]))] {
+            let bytes = bundle.to_bytes();
+            assert_eq!(JwtProof::from_bytes(&bytes).unwrap(), bundle);
+
+            assert!(matches!(
+                JwtProof::from_bytes(&bytes[..bytes.len() - 1]),
+                Err(BundleError::Malformed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn instances_round_trip() {
+        for bundle in [bundle(None), bundle(Some(vec![0xc0; 256]))] {
+            let instances = bundle.instances::<Fr>();
+            assert_eq!(
+                JwtPublicInputs::from_instances(&instances[0]).unwrap(),
+                bundle.public_inputs
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bundle = bundle(None);
+        bundle.version = FORMAT_VERSION + 1;
+
+        assert!(matches!(
+            JwtProof::from_json(&bundle.to_json().unwrap()),
+            Err(BundleError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            JwtProof::from_bytes(&bundle.to_bytes()),
+            Err(BundleError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn modulus_keeps_its_width() {
+        let short = bundle(Some(vec![0xc0; 255]));
+        // Leading zero limbs, as a modulus decoded from the instances may have
+        let mut modulus = vec![0xc0; 256];
+        modulus[..16].fill(0);
+        let bundle = bundle(Some(modulus));
+
+        let instances = bundle.instances::<Fr>();
+        assert_eq!(JwtPublicInputs::from_instances(&instances[0]).unwrap(), bundle.public_inputs);
+        assert_eq!(JwtProof::from_bytes(&bundle.to_bytes()).unwrap(), bundle);
+        assert_eq!(JwtProof::from_json(&bundle.to_json().unwrap()).unwrap(), bundle);
+
+        assert!(matches!(JwtProof::from_bytes(&short.to_bytes()), Err(BundleError::Malformed(_))));
+        assert!(matches!(
+            JwtProof::from_json(&short.to_json().unwrap()),
+            Err(BundleError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+//! Range checked bytes of 32-bit words, for comparing claims byte by byte.
+//!
+//! A word is split into four 8-bit limbs by the `RangeChip` of halo2wrong, each range
+//! checked through its byte lookup table, so a word takes a couple of rows instead of
+//! the 32 boolean cells of `MainGate::to_bits`.
+
+use ff::PrimeField;
+use halo2_maingate::{
+    AssignedValue, MainGate, MainGateInstructions, RangeChip, RangeInstructions, RegionCtx,
+    Term,
+};
+use halo2_proofs::{circuit::Value, plonk::Error};
+
+use crate::rsa::RANGE_LIMB_BITS;
+
+/// Bytes of a 32-bit word.
+pub const WORD_BYTES: usize = 4;
+
+/// A chip splitting words into bytes, sharing the byte table of the range checks.
+#[derive(Clone, Debug)]
+pub struct BytesChip<F: PrimeField> {
+    main_gate: MainGate<F>,
+    range_chip: RangeChip<F>,
+}
+
+impl<F: PrimeField> BytesChip<F> {
+    /// The range chip must be configured with 8-bit limbs, and its table loaded once per
+    /// circuit.
+    pub fn new(main_gate: MainGate<F>, range_chip: RangeChip<F>) -> Self {
+        Self { main_gate, range_chip }
+    }
+
+    /// Assigns `word` with its bytes, most significant first.
+    pub fn assign_word(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        word: Value<u32>,
+    ) -> Result<(AssignedValue<F>, [AssignedValue<F>; WORD_BYTES]), Error> {
+        let (word, mut bytes) = self.range_chip.decompose(
+            ctx,
+            word.map(|word| F::from(word as u64)),
+            RANGE_LIMB_BITS,
+            WORD_BYTES * 8,
+        )?;
+        bytes.reverse();
+
+        Ok((word, bytes.try_into().expect("a word has 4 bytes")))
+    }
+
+    /// Splits an assigned word into its bytes, most significant first. Fails to
+    /// satisfy the circuit if `word` does not fit in 32 bits.
+    pub fn decompose_word(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        word: &AssignedValue<F>,
+    ) -> Result<[AssignedValue<F>; WORD_BYTES], Error> {
+        let value = word.value().map(|word| {
+            u32::from_le_bytes(word.to_repr().as_ref()[..WORD_BYTES].try_into().unwrap())
+        });
+        let (composed, bytes) = self.assign_word(ctx, value)?;
+        self.main_gate.assert_equal(ctx, &composed, word)?;
+
+        Ok(bytes)
+    }
+
+    /// Constrains two runs of bytes to be equal.
+    pub fn assert_bytes_equal(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        a: &[AssignedValue<F>],
+        b: &[AssignedValue<F>],
+    ) -> Result<(), Error> {
+        if a.len() != b.len() {
+            return Err(Error::Synthesis);
+        }
+        for (a, b) in a.iter().zip(b) {
+            self.main_gate.assert_equal(ctx, a, b)?;
+        }
+
+        Ok(())
+    }
+
+    /// Constrains `needle` to be the bytes of `haystack` from a private `position`,
+    /// which may sit anywhere within words. The haystack is shifted by each bit of the
+    /// position in turn, a barrel shifter, so the layout only depends on the lengths and
+    /// takes `O(haystack * log(haystack))` rows whatever the length of the needle.
+    pub fn assert_included(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        haystack: &[AssignedValue<F>],
+        needle: &[AssignedValue<F>],
+        position: Value<usize>,
+    ) -> Result<(), Error> {
+        let last_start = haystack.len().checked_sub(needle.len()).ok_or(Error::Synthesis)?;
+        let number_of_bits = ((usize::BITS - last_start.leading_zeros()) as usize).max(1);
+
+        let position = self
+            .main_gate
+            .assign_value(ctx, position.map(|position| F::from(position as u64)))?;
+        let bits = self.main_gate.to_bits(ctx, &position, number_of_bits)?;
+        // The position is at most the last start
+        let gap = self.main_gate.compose(
+            ctx,
+            &[Term::Assigned(&position, -F::ONE)],
+            F::from(last_start as u64),
+        )?;
+        self.main_gate.to_bits(ctx, &gap, number_of_bits)?;
+
+        // After the first `i` bits, `shifted[j]` is the byte at `j` plus the low `i` bits
+        // of the position. Bytes the higher bits cannot bring in front of the needle are
+        // dropped, and a byte shifted in from past the end can only be one the needle
+        // does not reach, so it is left as is.
+        let mut shifted = haystack.to_vec();
+        for (i, bit) in bits.iter().enumerate() {
+            let shift = 1 << i;
+            let reach = (1 << number_of_bits) - (shift << 1);
+            let len = shifted.len().min(needle.len() + reach);
+            shifted = (0..len)
+                .map(|j| match shifted.get(j + shift) {
+                    Some(next) => self.main_gate.select(ctx, next, &shifted[j], bit),
+                    None => Ok(shifted[j].clone()),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+
+        self.assert_bytes_equal(ctx, &shifted[..needle.len()], needle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BytesChip;
+    use crate::rsa::{RsaChip, RsaConfig};
+    use halo2_maingate::{MainGate, MainGateInstructions, RegionCtx};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2curves::bn256::Fr;
+
+    /// Splits `word` and compares its bytes with the bytes of `other` at `range`.
+    #[derive(Clone, Default)]
+    struct BytesCircuit {
+        word: u32,
+        other: u32,
+        range: std::ops::Range<usize>,
+    }
+
+    impl Circuit<Fr> for BytesCircuit {
+        type Config = RsaConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let main_gate_config = MainGate::<Fr>::configure(meta);
+            RsaConfig::configure(meta, main_gate_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let rsa_chip = RsaChip::<Fr>::new(config);
+            rsa_chip.load(&mut layouter)?;
+            let chip = BytesChip::new(rsa_chip.main_gate().clone(), rsa_chip.range_chip().clone());
+
+            layouter.assign_region(
+                || "bytes",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let word = rsa_chip
+                        .main_gate()
+                        .assign_value(ctx, Value::known(Fr::from(self.word as u64)))?;
+                    let bytes = chip.decompose_word(ctx, &word)?;
+                    for (byte, expected) in bytes.iter().zip(self.word.to_be_bytes()) {
+                        byte.value().assert_if_known(|byte| **byte == Fr::from(expected as u64));
+                    }
+
+                    let (_, other) = chip.assign_word(ctx, Value::known(self.other))?;
+                    chip.assert_bytes_equal(ctx, &bytes[self.range.clone()], &other[self.range.clone()])
+                },
+            )
+        }
+    }
+
+    fn verify(circuit: &BytesCircuit) -> bool {
+        MockProver::<Fr>::run(10, circuit, vec![]).unwrap().verify().is_ok()
+    }
+
+    /// Claims `needle` is in `haystack` at the private `position`.
+    #[derive(Clone, Default)]
+    struct IncludedCircuit {
+        haystack: Vec<u8>,
+        needle: Vec<u8>,
+        position: usize,
+    }
+
+    impl Circuit<Fr> for IncludedCircuit {
+        type Config = RsaConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let main_gate_config = MainGate::<Fr>::configure(meta);
+            RsaConfig::configure(meta, main_gate_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let rsa_chip = RsaChip::<Fr>::new(config);
+            rsa_chip.load(&mut layouter)?;
+            let gate = rsa_chip.main_gate().clone();
+            let chip = BytesChip::new(gate.clone(), rsa_chip.range_chip().clone());
+
+            layouter.assign_region(
+                || "included",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let mut assign = |bytes: &[u8]| {
+                        bytes
+                            .iter()
+                            .map(|byte| gate.assign_value(ctx, Value::known(Fr::from(*byte as u64))))
+                            .collect::<Result<Vec<_>, _>>()
+                    };
+                    let haystack = assign(&self.haystack)?;
+                    let needle = assign(&self.needle)?;
+                    chip.assert_included(ctx, &haystack, &needle, Value::known(self.position))
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn includes_bytes_at_a_private_position() {
+        let circuit = |position| IncludedCircuit {
+            haystack: b"sub=twitter|337".to_vec(),
+            needle: b"twitter".to_vec(),
+            position,
+        };
+        let satisfied = |circuit: &IncludedCircuit| {
+            MockProver::<Fr>::run(10, circuit, vec![]).unwrap().verify().is_ok()
+        };
+
+        assert!(satisfied(&circuit(4)));
+        assert!(!satisfied(&circuit(3)));
+        assert!(!satisfied(&circuit(5)));
+        // Past the last start
+        assert!(!satisfied(&circuit(9)));
+        assert!(!satisfied(&circuit(100)));
+    }
+
+    #[test]
+    fn compares_bytes() {
+        let circuit = |range| BytesCircuit { word: 0x0102_0304, other: 0xff02_03ff, range };
+
+        assert!(verify(&circuit(1..3)));
+        assert!(verify(&circuit(2..2)));
+        assert!(!verify(&circuit(0..2)));
+        assert!(!verify(&circuit(2..4)));
+    }
+}
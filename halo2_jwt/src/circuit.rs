@@ -1,59 +1,208 @@
+use std::time::Instant;
+
 use halo2_maingate::{MainGateConfig, MainGate, MainGateInstructions, RegionCtx};
 use halo2_proofs::{
     circuit::{Layouter, SimpleFloorPlanner, Value},
     plonk::{Circuit, ConstraintSystem, Error},
 };
+use ff::PrimeField;
 use halo2curves::bn256::Fr;
 
+use crate::bytes::BytesChip;
+use crate::layout::{measure, CircuitDimensions};
 use crate::precompute::PreComputed;
-use crate::sha256::{Table16Config, Table16Chip, Sha256};
+use crate::pss::{Ps256Chip, Ps256PreComputed};
+use crate::rsa::{RsaChip, RsaConfig};
+use crate::sha256::{SpreadTableKind, Table16Config, Table16Chip, Sha256};
 
 #[derive(Debug, Clone)]
-pub struct JwtCircuitConfig {
-    pub sha256_config: Table16Config,
+pub struct JwtCircuitConfig<F: PrimeField> {
+    pub sha256_config: Table16Config<F>,
     pub maingate_config: MainGateConfig,
+    /// The byte range checks of `PS256`. The configuration cannot depend on the
+    /// algorithm, so every circuit has their lookup argument, a few more commitments
+    /// and evaluations per proof, but only `PS256` circuits load their table.
+    pub rsa_config: RsaConfig,
 }
 
+/// The signature algorithm verified alongside the JWT digest.
+#[derive(Debug, Clone, Default)]
+pub enum JwtAlgorithm {
+    /// Only the digest of the JWT is proven, the signature is checked elsewhere.
+    #[default]
+    None,
+    /// RSASSA-PSS with SHA-256 over a public 2048-bit modulus.
+    Ps256(Ps256PreComputed),
+}
+
+impl JwtAlgorithm {
+    /// The JWS `alg` name, or `none`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            JwtAlgorithm::None => "none",
+            JwtAlgorithm::Ps256(_) => "PS256",
+        }
+    }
+}
+
+/// What fixes the layout of a [`JwtCircuit`], and so its keys: a key only proves and
+/// verifies tokens of the same params.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JwtCircuitParams {
+    /// The circuit has `2^k` rows.
+    pub k: u32,
+    pub jwt_len: usize,
+    pub credential_len: usize,
+    /// Whether a `PS256` signature is verified.
+    pub ps256: bool,
+}
+
+impl JwtCircuitParams {
+    pub const ENCODED_LEN: usize = 4 + 2 * 8 + 1;
+
+    /// A fixed size big-endian encoding.
+    pub fn to_bytes(&self) -> [u8; Self::ENCODED_LEN] {
+        let mut result = [0u8; Self::ENCODED_LEN];
+        result[0..4].copy_from_slice(&self.k.to_be_bytes());
+        result[4..12].copy_from_slice(&(self.jwt_len as u64).to_be_bytes());
+        result[12..20].copy_from_slice(&(self.credential_len as u64).to_be_bytes());
+        result[20] = self.ps256 as u8;
+        result
+    }
+
+    /// Lays out a circuit for a dummy token of these params, and for `PS256` a
+    /// placeholder signature that is never checked.
+    pub fn dry_run(&self) -> Option<CircuitDimensions> {
+        if self.credential_len == 0 || self.credential_len > self.jwt_len {
+            return None;
+        }
+
+        // The layout only depends on the lengths
+        let mut jwt = vec![b'.'; self.jwt_len];
+        let credential = vec![b'a'; self.credential_len];
+        jwt[..self.credential_len].copy_from_slice(&credential);
+
+        let algorithm = if self.ps256 {
+            JwtAlgorithm::Ps256(Ps256PreComputed::placeholder())
+        } else {
+            JwtAlgorithm::None
+        };
+        JwtCircuit::new_with_algorithm(PreComputed::new_with_bytes(&jwt, &credential), algorithm)
+            .dimensions()
+            .ok()
+    }
+
+    /// These params with the smallest `k` they fit in.
+    pub fn with_min_k(&self) -> Option<Self> {
+        let k = self.dry_run()?.min_k();
+        Some(Self { k, ..*self })
+    }
+
+    pub fn from_bytes(bytes: &[u8; Self::ENCODED_LEN]) -> Option<Self> {
+        let usize_at = |i: usize| {
+            usize::try_from(u64::from_be_bytes(bytes[i..i + 8].try_into().unwrap())).ok()
+        };
+
+        Some(Self {
+            k: u32::from_be_bytes(bytes[0..4].try_into().unwrap()),
+            jwt_len: usize_at(4)?,
+            credential_len: usize_at(12)?,
+            ps256: match bytes[20] {
+                0 => false,
+                1 => true,
+                _ => return None,
+            },
+        })
+    }
+}
+
+/// Proves a JWT and the credential it contains. The circuit is not tied to a field, so
+/// it can be proven over BN254 with KZG as well as over Pasta with IPA. Its `Debug`
+/// output redacts the witness.
 #[derive(Debug, Clone, Default)]
 pub struct JwtCircuit {
     precomputed: PreComputed,
+    algorithm: JwtAlgorithm,
 }
 
 impl JwtCircuit {
     pub fn new(precomputed: PreComputed) -> Self {
-        Self { precomputed }
+        Self::new_with_algorithm(precomputed, JwtAlgorithm::None)
+    }
+
+    pub fn new_with_algorithm(precomputed: PreComputed, algorithm: JwtAlgorithm) -> Self {
+        Self { precomputed, algorithm }
     }
 
-    pub fn public_inputs(&self) -> Vec<Fr> {
-        self.precomputed.public_inputs()
+    pub fn algorithm(&self) -> &JwtAlgorithm {
+        &self.algorithm
+    }
+
+    /// The params of this circuit when laid out in `2^k` rows.
+    pub fn params(&self, k: u32) -> JwtCircuitParams {
+        JwtCircuitParams {
+            k,
+            jwt_len: self.precomputed.jwt_len(),
+            credential_len: self.precomputed.credential_len(),
+            ps256: matches!(self.algorithm, JwtAlgorithm::Ps256(_)),
+        }
+    }
+
+    /// Dry-runs the layout of this circuit. The layout does not depend on the field, so
+    /// it is measured over BN254.
+    pub fn dimensions(&self) -> Result<CircuitDimensions, Error> {
+        measure::<Fr, _>(self)
+    }
+
+    /// The params of this circuit laid out in as few rows as it fits in.
+    pub fn min_params(&self) -> Result<JwtCircuitParams, Error> {
+        Ok(self.params(self.dimensions()?.min_k()))
+    }
+
+    /// The 16 JWT public inputs, the digests of the JWT and of the credential, followed
+    /// by the modulus limbs for `PS256`.
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<F> {
+        let mut result = self.precomputed.public_inputs();
+        if let JwtAlgorithm::Ps256(ps256) = &self.algorithm {
+            result.extend(ps256.public_inputs());
+        }
+
+        result
     }
 }
 
-impl Circuit<Fr> for JwtCircuit {
+impl<F: PrimeField> Circuit<F> for JwtCircuit {
 
-    type Config = JwtCircuitConfig;
+    type Config = JwtCircuitConfig<F>;
     type FloorPlanner = SimpleFloorPlanner;
 
     fn without_witnesses(&self) -> Self {
         Self::default()
     }
 
-    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let maingate_config = MainGate::<F>::configure(meta);
         Self::Config {
-            sha256_config: Table16Chip::configure(meta), 
-            maingate_config: MainGate::<Fr>::configure(meta),
+            // Short tokens take far fewer rows than the 2^16 of a full spread table
+            sha256_config: Table16Chip::configure_with_table(meta, SpreadTableKind::Split),
+            rsa_config: RsaConfig::configure(meta, maingate_config.clone()),
+            maingate_config,
         }
     }
 
-    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let start = Instant::now();
 
         /* START Pre-Constrained Zone */
-        let [preimage_jwt, preimage_credential] = self.precomputed.preimage_as_blockwords();
-        let [expected_digest_jwt, expected_digest_credential] = self.precomputed.expected_digest_as_blockwords();
-        let (segment_location_start, segment_location_end) = self.precomputed.segment_location();
-        let (segment_start_offset, segment_end_offset) = self.precomputed.segment_offset();
+        let [preimage_jwt_bytes, preimage_credential_bytes] = self.precomputed.preimage_as_bytes();
+        let credential_position = self.precomputed.credential_position();
+        let jwt_len = self.precomputed.jwt_len();
+        let credential_len = self.precomputed.credential_len();
 
         self.precomputed.log_all();
+        if let JwtAlgorithm::Ps256(ps256) = &self.algorithm {
+            ps256.log_all();
+        }
         /* END Pre-Constrained Zone */
 
         /* START In-Constrained Zone */
@@ -61,161 +210,95 @@ impl Circuit<Fr> for JwtCircuit {
         let sha256_chip = Table16Chip::construct(config.sha256_config.clone());
         Table16Chip::load(config.sha256_config.clone(), &mut layouter.namespace(|| "table16_chip"))?;
 
-        let gate = MainGate::<Fr>::new(config.maingate_config.clone());
-        // digest + IV = expected_digest
-        let partial_digest_jwt = Sha256::digest(
+        let gate = MainGate::<F>::new(config.maingate_config.clone());
+        let rsa_chip = RsaChip::<F>::new(config.rsa_config.clone());
+        // Without range checks the lookup inputs are all zero, which an empty table holds
+        if let JwtAlgorithm::Ps256(_) = &self.algorithm {
+            rsa_chip.load(&mut layouter.namespace(|| "range_table"))?;
+        }
+        let bytes_chip = BytesChip::new(gate.clone(), rsa_chip.range_chip().clone());
+        let ps256_chip = Ps256Chip::new(rsa_chip);
+
+        // The JWT and the credential are hashed byte by byte, so their bytes are cells
+        // the inclusion proof and other gadgets can use. Their SHA-256 padding only
+        // depends on the length, so it is fixed by the layout.
+        let jwt_bytes = layouter.namespace(|| "jwt bytes").assign_region(|| "jwt bytes", |region| {
+            let ctx = &mut RegionCtx::new(region, 0);
+            let (jwt, padding) = preimage_jwt_bytes.split_at(jwt_len);
+
+            let mut jwt = jwt
+                .iter()
+                .map(|byte| gate.assign_value(ctx, Value::known(F::from(*byte as u64))))
+                .collect::<Result<Vec<_>, _>>()?;
+            for byte in padding {
+                jwt.push(gate.assign_constant(ctx, F::from(*byte as u64))?);
+            }
+            Ok(jwt)
+        })?;
+
+        // 2. conduct the inclusion proof
+        // JWT      0x01 0x02 0x03 0x04 | 0x05 0x06 0x07 0x08
+        // Credential         0x03 0x04 | 0x05
+        // The credential is selected from the hashed JWT at a private position, whatever
+        // the alignment, and followed by its SHA-256 padding.
+        let credential_bytes = layouter.namespace(|| "credential bytes").assign_region(|| "credential bytes", |region| {
+            let ctx = &mut RegionCtx::new(region, 0);
+            let (credential, padding) = preimage_credential_bytes.split_at(credential_len);
+
+            let mut credential = credential
+                .iter()
+                .map(|byte| gate.assign_value(ctx, Value::known(F::from(*byte as u64))))
+                .collect::<Result<Vec<_>, _>>()?;
+            bytes_chip.assert_included(
+                ctx,
+                &jwt_bytes[..jwt_len],
+                &credential,
+                Value::known(credential_position),
+            )?;
+
+            for byte in padding {
+                credential.push(gate.assign_constant(ctx, F::from(*byte as u64))?);
+            }
+            Ok(credential)
+        })?;
+
+        // 3. hash both, the digests are the cells the SHA-256 gates constrain
+        let digest_jwt = Sha256::digest_bytes_assigned(
             sha256_chip.clone(), 
             layouter.namespace(|| "sha256_jwt"), 
-            &preimage_jwt
+            &jwt_bytes
         )?;
 
-        let partial_digest_credential = Sha256::digest(
-            sha256_chip, 
+        let digest_credential = Sha256::digest_bytes_assigned(
+            sha256_chip.clone(), 
             layouter.namespace(|| "sha256_credential"), 
-            &preimage_credential
+            &credential_bytes
         )?;
 
-        // 2. conduct the inclusion proof
-        let (
-            expected_digest_jwt_value, 
-            expected_digest_credential_value,
-            start_offset,
-            end_offset,
-        ) = layouter.namespace(|| "full_proof").assign_region(|| "full proof", |region| {
-
-            let offset = 0;
-            let ctx = &mut RegionCtx::new(region, offset);
-
-            // INCLUSION PROOF
-            let (start_offset, end_offset) = {
-                // STARTING SEGMENT
-                if segment_start_offset != 0 {
-                    // JWT 0x1 0x2 0x3 0x4 & Credential 0x00 0x00 0x03 0x04
-
-                    // 1. load both JWTand Credential value into the constrain sys
-                    let jwt_value = gate.assign_value(ctx,
-                        preimage_jwt[segment_location_start].0
-                            .map(|x| Fr::from(x as u64))
-                    )?;
-
-                    let credential_value = gate.assign_value(ctx,
-                        preimage_credential[0].0
-                            .map(|x| Fr::from(x as u64))
-                    )?;
-
-                    // 2. sub JWT to Credential -> to get 0x0102_0000 and convert to be_bits
-                    let sub = gate.sub(ctx, &jwt_value, &credential_value)?;
-                    let sub_bits = gate.to_bits(ctx, &sub, 32)?;
-
-                    assert_eq!(sub_bits.len(), 32);
-                    // Numbers of Ending Zero = segment_start_offset * 2
-                    // Numbers of Ending Bits in Zero = Segment_start_offset * 8
-                    for bit_index in 0..segment_start_offset * 8 {
-                        gate.assert_zero(ctx, &sub_bits[bit_index])?;
-                    }
-                }
-
-                let mut segment_offset = 1;
-                loop {
-                    // we break early - to correctly handle the end
-                    if segment_location_start + segment_offset >= segment_location_end {
-                        break;
-                    }
-
-                    let jwt_value = gate.assign_value(ctx, 
-                        preimage_jwt[segment_location_start + segment_offset].0
-                        .map(|x| Fr::from(x as u64)))?;
-                    let credential_value = gate.assign_value(ctx, 
-                        preimage_credential[segment_offset].0
-                        .map(|x| Fr::from(x as u64)))?;
-
-
-                    log::info!("{:?} {:?} {:?}", segment_offset, jwt_value, credential_value);
-                    gate.assert_equal(ctx,  &jwt_value, &credential_value)?;
-
-                    segment_offset += 1;
-                }
-
-                // ENDING SEGMENT
-                if segment_end_offset != 0 {
-                    // JWT 0x1 0x2 0x3 0x4 & Credential 0x10 0x20 0x00 0x00
-
-                    let jwt_value = gate.assign_value(ctx,
-                        preimage_jwt[segment_location_end].0
-                            .map(|x| Fr::from(x as u64))
-                    )?;
-
-                    let credential_value = gate.assign_value(ctx,
-                        preimage_credential[segment_offset].0
-                            .map(|x| Fr::from(x as u64))
-                    )?;
-
-                   // 2. sub JWT to Credential -> to get 0x0000_0304 and convert to be_bits
-                   let sub = gate.sub(ctx, &jwt_value, &credential_value)?;
-                   let sub_bits = gate.to_bits(ctx, &sub, 32)?;
-
-                   assert_eq!(sub_bits.len(), 32);
-
-                   // Numbers of Leading Zero = segment_end_offset * 2
-                   // Numbers of Leading Bits in Zero = segment_end_offset * 8
-                   for bit_index in segment_end_offset * 8..32{
-                       gate.assert_zero(ctx, &sub_bits[bit_index])?;
-                   }
-                }
-
-                // assign segment_location_start and segment_location_end to the constrain 
-                // awaiting to be exposed as public inputs
-                (
-                    gate.assign_value(ctx, Value::known(Fr::from(segment_start_offset as u64)))?,
-                    gate.assign_value(ctx, Value::known(Fr::from(segment_end_offset as u64)))?
-                )
-            };
-
-            // SHA256 PROOF
-            let (expected_digest_jwt_value, expected_digest_credential_value) = {
-
-                // We would record the loaded digest cell .. so that they could be exposed to public
-                let mut expected_digest_jwt_value = Vec::with_capacity(8);
-                let mut expected_digest_credential_value = Vec::with_capacity(8);
-
-                // https://stepansnigirev.github.io/visual-sha256/
-                for index in 0..8 {
-                    log::info!("[Constrained] Iterating SHA256 Proof at Loc {:?}", index);
-
-                    // 1. assign digest to proof
-                    let partial_digest_jwt = gate.assign_value(ctx, partial_digest_jwt.0[index].0.map(|x| Fr::from(x as u64)))?;
-                    let partial_digest_credential = gate.assign_value(ctx, partial_digest_credential.0[index].0.map(|x| Fr::from(x as u64)))?;
-
-                    // 2. assign expected digest 
-                    let expected_digest_jwt = gate.assign_value(ctx, expected_digest_jwt[index].0.map(|x| Fr::from(x as u64)))?;
-                    let expected_digest_credential = gate.assign_value(ctx, expected_digest_credential[index].0.map(|x| Fr::from(x as u64)))?;
-
-                    // 3. compare
-                    gate.assert_equal(ctx, &partial_digest_jwt, &expected_digest_jwt)?;
-                    gate.assert_equal(ctx, &partial_digest_credential, &expected_digest_credential)?;
-                    
-                    expected_digest_jwt_value.push(expected_digest_jwt);
-                    expected_digest_credential_value.push(expected_digest_credential);
-                }
-
-                (expected_digest_jwt_value, expected_digest_credential_value)
-            };
-
-            Ok((expected_digest_jwt_value, expected_digest_credential_value, start_offset, end_offset))
-        })?;
+        // SIGNATURE PROOF, over the digest of the JWT
+        let modulus = match &self.algorithm {
+            JwtAlgorithm::None => None,
+            JwtAlgorithm::Ps256(ps256) => Some(ps256_chip.verify(
+                sha256_chip,
+                layouter.namespace(|| "signature_proof"),
+                ps256,
+                &digest_jwt,
+            )?),
+        };
 
         for i in 0..8 {
-            log::info!("[Constrained] Exposing SHA256 as Public Inputs at Loc {:?}", i);
-
-            gate.expose_public(layouter.namespace(|| "public_jwt_digest"), expected_digest_jwt_value[i].clone(), i)?;
-            gate.expose_public(layouter.namespace(|| "public_credential_digest"), expected_digest_credential_value[i].clone(), i + 8)?;
+            gate.expose_public(layouter.namespace(|| "public_jwt_digest"), digest_jwt[i].clone(), i)?;
+            gate.expose_public(layouter.namespace(|| "public_credential_digest"), digest_credential[i].clone(), i + 8)?;
         } 
 
-        gate.expose_public(layouter.namespace(|| "public_segment_start_offset"), start_offset, 16)?;
-        gate.expose_public(layouter.namespace(|| "public_segment_end_offset"), end_offset, 17)?;
+        if let Some(modulus) = modulus {
+            for (i, limb) in modulus.limbs().iter().enumerate() {
+                gate.expose_public(layouter.namespace(|| "public_modulus"), limb.clone(), 16 + i)?;
+            }
+        }
         /* END In-Constrained Zone */
 
-        log::info!("[END] Circuit & Witness Table Generated!");
+        log::info!("[END] Circuit & Witness Table Generated in {:?}", start.elapsed());
         Ok(())
     }
 }
@@ -0,0 +1,265 @@
+//! Cost reports of circuits: where the rows go, and what a proof costs to ship and to
+//! verify on chain.
+//!
+//! Regions are grouped by name, so the 64 rounds of every SHA-256 compression add up in
+//! `compress`, and by top-level namespace, so the JWT and the credential hashes are told
+//! apart.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use halo2_proofs::{
+    dev::CircuitCost,
+    plonk::{Circuit, Error, VerifyingKey},
+    poly::kzg::commitment::ParamsKZG,
+};
+use halo2curves::bn256::{Bn256, Fr, G1Affine, G1};
+use serde::{Deserialize, Serialize};
+use snark_verifier::{
+    cost::CostEstimation,
+    loader::{evm::estimate_gas, native::NativeLoader},
+    pcs::kzg::{Gwc19, KzgAs},
+    system::halo2::{compile, Config},
+    verifier::plonk::PlonkVerifier,
+};
+
+use crate::layout::{measure_regions, CircuitDimensions};
+
+/// Namespace of the regions assigned outside of any namespace.
+const ROOT_NAMESPACE: &str = "(root)";
+
+/// The regions sharing a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RegionCost {
+    /// How many regions have the name.
+    pub count: usize,
+    /// Rows spanned by all of them.
+    pub rows: usize,
+}
+
+/// The cost of a circuit laid out in as few rows as it fits in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostReport {
+    pub k: u32,
+    pub dimensions: CircuitDimensions,
+    pub regions: BTreeMap<String, RegionCost>,
+    /// Rows spanned by the regions under every top-level namespace.
+    pub namespaces: BTreeMap<String, usize>,
+    /// Size in bytes of a proof.
+    pub proof_size: usize,
+    /// Gas of a call to the EVM verifier, see [`CostReport::with_verifier_gas`].
+    pub verifier_gas: Option<usize>,
+}
+
+impl CostReport {
+    pub fn measure<C: Circuit<Fr>>(circuit: &C) -> Result<Self, Error> {
+        let (dimensions, layout) = measure_regions(circuit)?;
+        let k = dimensions.min_k();
+
+        let mut regions = BTreeMap::<_, RegionCost>::new();
+        let mut namespaces = BTreeMap::new();
+        for region in layout {
+            let cost = regions.entry(region.name).or_default();
+            cost.count += 1;
+            cost.rows += region.rows;
+
+            let namespace = region.namespace.first().map_or(ROOT_NAMESPACE, String::as_str);
+            *namespaces.entry(namespace.to_string()).or_default() += region.rows;
+        }
+
+        let proof_size = CircuitCost::<G1, C>::measure(k.try_into().unwrap(), circuit).proof_size(1);
+
+        Ok(Self {
+            k,
+            dimensions,
+            regions,
+            namespaces,
+            proof_size: proof_size.into(),
+            verifier_gas: None,
+        })
+    }
+
+    /// Estimates the gas of verifying a proof with [`crate::proof::gen_evm_verifier`],
+    /// which needs the verifying key of the circuit.
+    pub fn with_verifier_gas(
+        mut self,
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        num_instance: Vec<usize>,
+    ) -> Self {
+        self.verifier_gas = Some(verifier_gas(params, vk, num_instance));
+        self
+    }
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.dimensions)?;
+        writeln!(f, "proof size: {} bytes", self.proof_size)?;
+        if let Some(gas) = self.verifier_gas {
+            writeln!(f, "verifier gas: {}", gas)?;
+        }
+
+        writeln!(f, "rows by namespace:")?;
+        for (namespace, rows) in self.namespaces.iter() {
+            writeln!(f, "  {:<32} {:>8}", namespace, rows)?;
+        }
+        writeln!(f, "rows by region:")?;
+        for (name, cost) in self.regions.iter() {
+            writeln!(f, "  {:<32} {:>8} in {} regions", name, cost.rows, cost.count)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Estimates the gas of verifying a GWC proof on chain, from the shape of the verifier.
+pub fn verifier_gas(params: &ParamsKZG<Bn256>, vk: &VerifyingKey<G1Affine>, num_instance: Vec<usize>) -> usize {
+    let protocol = compile(params, vk, Config::kzg().with_num_instance(num_instance));
+    let cost = <PlonkVerifier<KzgAs<Bn256, Gwc19>> as CostEstimation<(G1Affine, NativeLoader)>>::estimate_cost(
+        &protocol,
+    );
+    estimate_gas(cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CostReport;
+    use crate::circuit::JwtCircuit;
+    use crate::precompute::PreComputed;
+    use crate::proof::{Backend, Kzg};
+    use crate::sha256::{BlockWord, Sha256, Table16Chip, Table16Config};
+    use crate::util::{bytes_to_u32_array, pad_sha256_bytes, u32_array_to_blockwords};
+
+    use std::path::PathBuf;
+
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{keygen_vk, Circuit, ConstraintSystem, Error},
+    };
+    use halo2curves::bn256::Fr;
+
+    /// Compares `report` with the committed snapshot of `name`, failing if anything costs
+    /// more or if there is no snapshot. Setting `UPDATE_SNAPSHOTS` writes the snapshot
+    /// instead, to record a new circuit or a cost change on purpose.
+    fn check_snapshot(name: &str, report: &CostReport) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("snapshots/cost")
+            .join(format!("{}.json", name));
+
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, serde_json::to_string_pretty(report).unwrap()).unwrap();
+            return;
+        }
+        assert!(
+            path.exists(),
+            "no cost snapshot at {}, rerun with UPDATE_SNAPSHOTS=1 and commit it:\n{}",
+            path.display(),
+            report
+        );
+        let snapshot: CostReport = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+
+        let mut increases = Vec::new();
+        let mut compare = |what: String, before: usize, after: usize| {
+            if after > before {
+                increases.push(format!("{}: {} -> {}", what, before, after));
+            }
+        };
+        compare("k".into(), snapshot.k as usize, report.k as usize);
+        compare("rows".into(), snapshot.dimensions.rows, report.dimensions.rows);
+        compare("advice columns".into(), snapshot.dimensions.advice_columns, report.dimensions.advice_columns);
+        compare("fixed columns".into(), snapshot.dimensions.fixed_columns, report.dimensions.fixed_columns);
+        compare("lookups".into(), snapshot.dimensions.lookups, report.dimensions.lookups);
+        compare("proof size".into(), snapshot.proof_size, report.proof_size);
+        compare(
+            "verifier gas".into(),
+            snapshot.verifier_gas.unwrap_or_default(),
+            report.verifier_gas.unwrap_or_default(),
+        );
+        for (name, cost) in report.regions.iter() {
+            let before = snapshot.regions.get(name).copied().unwrap_or_default();
+            compare(format!("region {}", name), before.rows, cost.rows);
+        }
+        for (namespace, rows) in report.namespaces.iter() {
+            let before = snapshot.namespaces.get(namespace).copied().unwrap_or_default();
+            compare(format!("namespace {}", namespace), before, *rows);
+        }
+
+        assert!(
+            increases.is_empty(),
+            "{} costs more than its snapshot, rerun with UPDATE_SNAPSHOTS=1 if intended:\n{}\n{}",
+            name,
+            increases.join("\n"),
+            report
+        );
+    }
+
+    #[test]
+    fn jwt_circuit_cost() {
+        let jwt = "{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\"}";
+        let credential = "twitter|337834122";
+        let circuit = JwtCircuit::new(PreComputed::new(jwt, credential));
+
+        let report = CostReport::measure(&circuit).unwrap();
+        assert!(report.regions.contains_key("compress"));
+        assert!(report.regions.contains_key("credential bytes"));
+        assert!(report.namespaces.contains_key("sha256_jwt"));
+        assert_eq!(report.namespaces.values().sum::<usize>(), report.regions.values().map(|cost| cost.rows).sum::<usize>());
+
+        let params = Kzg::setup(report.k);
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        let report = report.with_verifier_gas(&params, &vk, vec![circuit.public_inputs::<Fr>().len()]);
+
+        check_snapshot("jwt_circuit", &report);
+    }
+
+    #[test]
+    fn credential_inclusion_cost() {
+        // The credential is selected by a barrel shifter, so a longer one adds rows for
+        // its own bytes, not for each of them at every start in the JWT
+        let jwt = format!("{{\"sub\":\"{}\",\"aud\":\"123\"}}", "x".repeat(512));
+        let rows = |credential_len: usize| {
+            let circuit = JwtCircuit::new(PreComputed::new(&jwt, &"x".repeat(credential_len)));
+            CostReport::measure(&circuit).unwrap().regions["credential bytes"].rows
+        };
+
+        assert!(rows(64) - rows(32) < jwt.len());
+    }
+
+    #[test]
+    fn table16_single_block_cost() {
+        struct SingleBlock;
+
+        impl Circuit<Fr> for SingleBlock {
+            type Config = Table16Config<Fr>;
+            type FloorPlanner = SimpleFloorPlanner;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
+
+            fn without_witnesses(&self) -> Self {
+                SingleBlock
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+                let table16_chip = Table16Chip::construct(config.clone());
+                Table16Chip::load(config, &mut layouter)?;
+
+                let input: Vec<BlockWord> = u32_array_to_blockwords(&bytes_to_u32_array(&pad_sha256_bytes(b"abc")));
+                Sha256::digest(table16_chip, layouter.namespace(|| "sha256"), &input)?;
+
+                Ok(())
+            }
+        }
+
+        let report = CostReport::measure(&SingleBlock).unwrap();
+        assert_eq!(report.regions["compress"].count, 1);
+        assert_eq!(report.regions["process message block"].count, 1);
+
+        check_snapshot("table16_single_block", &report);
+    }
+}
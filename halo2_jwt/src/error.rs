@@ -0,0 +1,63 @@
+use std::fmt;
+
+use halo2_proofs::plonk::Error;
+
+use crate::bundle::BundleError;
+
+/// Errors returned to the relying party verifying a JWT proof.
+#[derive(Debug)]
+pub enum JwtError {
+    /// The public inputs do not decode into JWT claims.
+    InvalidPublicInputs,
+    /// The proof does not verify against the verifying key and public inputs.
+    ProofRejected,
+    /// The proof bundle could not be decoded.
+    Bundle(BundleError),
+    /// The token does not have the circuit params the keys were generated for.
+    ParamsMismatch,
+    /// The prover failed.
+    Prover(Error),
+    /// An aggregation circuit without room for any proof.
+    ZeroCapacity,
+    /// More proofs than an aggregation circuit has room for.
+    CapacityExceeded { capacity: usize, count: usize },
+    /// The snark is not a step of the chain it was folded into.
+    ChainMismatch,
+    /// The token does not come after the last one folded into the chain.
+    TokenOutOfOrder,
+}
+
+impl fmt::Display for JwtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwtError::InvalidPublicInputs => write!(f, "invalid public inputs"),
+            JwtError::ProofRejected => write!(f, "proof rejected"),
+            JwtError::Bundle(e) => write!(f, "{}", e),
+            JwtError::ParamsMismatch => write!(f, "token does not match the circuit params"),
+            JwtError::Prover(e) => write!(f, "prover failed: {:?}", e),
+            JwtError::ZeroCapacity => write!(f, "aggregation capacity must be positive"),
+            JwtError::CapacityExceeded { capacity, count } => {
+                write!(f, "{} proofs exceed the aggregation capacity of {}", count, capacity)
+            }
+            JwtError::ChainMismatch => write!(f, "snark is not a step of this chain"),
+            JwtError::TokenOutOfOrder => write!(f, "token does not come after the last one of the chain"),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
+
+impl From<Error> for JwtError {
+    fn from(e: Error) -> Self {
+        JwtError::Prover(e)
+    }
+}
+
+impl From<BundleError> for JwtError {
+    fn from(e: BundleError) -> Self {
+        match e {
+            BundleError::InvalidPublicInputs => JwtError::InvalidPublicInputs,
+            e => JwtError::Bundle(e),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Runs the tokens of `fixtures/jwt` through the circuit end to end, after checking
+//! their signature with the key they come with. The signature is only checked here:
+//! the circuit proves the payload digest and the claim, not the signature.
+
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use halo2_proofs::dev::MockProver;
+use halo2curves::bn256::Fr;
+use hmac::{Hmac, Mac};
+use p256::{ecdsa, EncodedPoint, FieldBytes};
+use rsa::{pkcs1v15::{Signature, VerifyingKey}, signature::Verifier, BigUint, RsaPublicKey};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::circuit::JwtCircuit;
+use crate::precompute::PreComputed;
+use crate::util::sha256_hash_bytes_digests;
+
+#[derive(Deserialize)]
+struct Fixture {
+    description: String,
+    token: String,
+    /// The claim whose value is proven to be in the payload.
+    claim: String,
+    key: Option<Jwk>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kty")]
+enum Jwk {
+    #[serde(rename = "oct")]
+    Oct { k: String },
+    #[serde(rename = "RSA")]
+    Rsa { n: String, e: String },
+    #[serde(rename = "EC")]
+    Ec { crv: String, x: String, y: String },
+}
+
+fn decode(part: &str) -> Vec<u8> {
+    URL_SAFE_NO_PAD.decode(part).unwrap()
+}
+
+fn fixtures() -> Vec<(String, Fixture)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/jwt");
+    let mut paths = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |extension| extension == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let fixture = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            (name, fixture)
+        })
+        .collect()
+}
+
+/// Checks the signature of `fixture`, returning its payload.
+fn verified_payload(name: &str, fixture: &Fixture) -> Vec<u8> {
+    let parts = fixture.token.split('.').collect::<Vec<_>>();
+    let [header, payload, signature] = parts[..] else {
+        panic!("{}: not a compact JWS", name);
+    };
+    let signing_input = format!("{}.{}", header, payload);
+    let signature = decode(signature);
+
+    let header: serde_json::Value = serde_json::from_slice(&decode(header)).unwrap();
+    match (header["alg"].as_str().unwrap(), &fixture.key) {
+        ("none", None) => assert!(signature.is_empty(), "{}: unsecured JWS with a signature", name),
+        ("HS256", Some(Jwk::Oct { k })) => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(&decode(k)).unwrap();
+            mac.update(signing_input.as_bytes());
+            mac.verify_slice(&signature).unwrap_or_else(|_| panic!("{}: bad signature", name));
+        }
+        ("RS256", Some(Jwk::Rsa { n, e })) => {
+            let key = RsaPublicKey::new(BigUint::from_bytes_be(&decode(n)), BigUint::from_bytes_be(&decode(e))).unwrap();
+            VerifyingKey::<Sha256>::new(key)
+                .verify(signing_input.as_bytes(), &Signature::try_from(&signature[..]).unwrap())
+                .unwrap_or_else(|_| panic!("{}: bad signature", name));
+        }
+        ("ES256", Some(Jwk::Ec { crv, x, y })) if crv == "P-256" => {
+            let point = EncodedPoint::from_affine_coordinates(
+                FieldBytes::from_slice(&decode(x)),
+                FieldBytes::from_slice(&decode(y)),
+                false,
+            );
+            ecdsa::VerifyingKey::from_encoded_point(&point)
+                .unwrap()
+                .verify(signing_input.as_bytes(), &ecdsa::Signature::from_slice(&signature).unwrap())
+                .unwrap_or_else(|_| panic!("{}: bad signature", name));
+        }
+        (alg, _) => panic!("{}: no key for {}", name, alg),
+    }
+
+    decode(payload)
+}
+
+#[test]
+fn fixture_tokens() {
+    let fixtures = fixtures();
+    assert!(fixtures.len() >= 9, "fixtures missing");
+
+    for (name, fixture) in fixtures.iter() {
+        log::info!("{}: {}", name, fixture.description);
+        let payload = verified_payload(name, fixture);
+
+        let claims: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        let credential = claims[&fixture.claim]
+            .as_str()
+            .unwrap_or_else(|| panic!("{}: no {} claim", name, fixture.claim));
+
+        let precomputed = PreComputed::new_with_bytes(&payload, credential.as_bytes());
+        let circuit = JwtCircuit::new(precomputed);
+        let public_inputs = circuit.public_inputs::<Fr>();
+
+        let digest = sha256_hash_bytes_digests(&payload);
+        for (word, bytes) in public_inputs[..8].iter().zip(digest.chunks(4)) {
+            assert_eq!(*word, Fr::from(u32::from_be_bytes(bytes.try_into().unwrap()) as u64), "{}", name);
+        }
+
+        let k = circuit.min_params().unwrap().k;
+        let prover = MockProver::run(k, &circuit, vec![public_inputs]).unwrap();
+        assert_eq!(prover.verify(), Ok(()), "{}", name);
+    }
+}
@@ -0,0 +1,182 @@
+//! Storage of `JwtCircuit` keys, so they are generated once and a verifier can be given
+//! the verifying key alone.
+//!
+//! A key file is a header followed by the key as written by halo2:
+//!
+//! | field                         | size                             |
+//! |-------------------------------|----------------------------------|
+//! | magic `JWVK` or `JWPK`        | 4                                |
+//! | backend name length `n`       | 1                                |
+//! | backend name                  | `n`                              |
+//! | [`JwtCircuitParams`]          | [`JwtCircuitParams::ENCODED_LEN`] |
+//! | [`SerdeFormat`]               | 1                                |
+//!
+//! The params are returned on load, so the caller can pick matching public parameters
+//! and check proofs against them.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use halo2_proofs::{
+    plonk::{ProvingKey, VerifyingKey},
+    SerdeFormat,
+};
+
+use crate::circuit::{JwtCircuit, JwtCircuitParams};
+use crate::proof::Backend;
+
+const VK_MAGIC: &[u8; 4] = b"JWVK";
+const PK_MAGIC: &[u8; 4] = b"JWPK";
+
+pub fn save_vk<B: Backend>(
+    path: &Path,
+    params: &JwtCircuitParams,
+    vk: &VerifyingKey<B::Affine>,
+    format: SerdeFormat,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_header::<B>(&mut writer, VK_MAGIC, params, format)?;
+    B::write_vk(vk, &mut writer, format)?;
+    writer.flush()
+}
+
+pub fn load_vk<B: Backend>(path: &Path) -> io::Result<(JwtCircuitParams, VerifyingKey<B::Affine>)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let (params, format) = read_header::<B>(&mut reader, VK_MAGIC)?;
+    Ok((params, B::read_vk::<_, JwtCircuit>(&mut reader, format)?))
+}
+
+pub fn save_pk<B: Backend>(
+    path: &Path,
+    params: &JwtCircuitParams,
+    pk: &ProvingKey<B::Affine>,
+    format: SerdeFormat,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_header::<B>(&mut writer, PK_MAGIC, params, format)?;
+    B::write_pk(pk, &mut writer, format)?;
+    writer.flush()
+}
+
+pub fn load_pk<B: Backend>(path: &Path) -> io::Result<(JwtCircuitParams, ProvingKey<B::Affine>)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let (params, format) = read_header::<B>(&mut reader, PK_MAGIC)?;
+    Ok((params, B::read_pk::<_, JwtCircuit>(&mut reader, format)?))
+}
+
+fn write_header<B: Backend>(
+    writer: &mut impl Write,
+    magic: &[u8; 4],
+    params: &JwtCircuitParams,
+    format: SerdeFormat,
+) -> io::Result<()> {
+    writer.write_all(magic)?;
+    writer.write_all(&[B::NAME.len() as u8])?;
+    writer.write_all(B::NAME.as_bytes())?;
+    writer.write_all(&params.to_bytes())?;
+    writer.write_all(&[format_to_byte(format)])
+}
+
+fn read_header<B: Backend>(
+    reader: &mut impl Read,
+    magic: &[u8; 4],
+) -> io::Result<(JwtCircuitParams, SerdeFormat)> {
+    let mut read_magic = [0u8; 4];
+    reader.read_exact(&mut read_magic)?;
+    if &read_magic != magic {
+        return Err(invalid_data("not a key file of this kind"));
+    }
+
+    let mut name_len = [0u8; 1];
+    reader.read_exact(&mut name_len)?;
+    let mut name = vec![0u8; name_len[0] as usize];
+    reader.read_exact(&mut name)?;
+    if name != B::NAME.as_bytes() {
+        return Err(invalid_data("key of another backend"));
+    }
+
+    let mut params = [0u8; JwtCircuitParams::ENCODED_LEN];
+    reader.read_exact(&mut params)?;
+    let params = JwtCircuitParams::from_bytes(&params).ok_or_else(|| invalid_data("invalid circuit params"))?;
+
+    let mut format = [0u8; 1];
+    reader.read_exact(&mut format)?;
+    let format = format_from_byte(format[0]).ok_or_else(|| invalid_data("invalid serde format"))?;
+
+    Ok((params, format))
+}
+
+fn format_to_byte(format: SerdeFormat) -> u8 {
+    match format {
+        SerdeFormat::Processed => 0,
+        SerdeFormat::RawBytes => 1,
+        SerdeFormat::RawBytesUnchecked => 2,
+    }
+}
+
+fn format_from_byte(byte: u8) -> Option<SerdeFormat> {
+    match byte {
+        0 => Some(SerdeFormat::Processed),
+        1 => Some(SerdeFormat::RawBytes),
+        2 => Some(SerdeFormat::RawBytesUnchecked),
+        _ => None,
+    }
+}
+
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_pk, load_vk, save_pk, save_vk};
+    use crate::bundle::{vk_hash, JwtProof};
+    use crate::circuit::JwtCircuit;
+    use crate::precompute::PreComputed;
+    use crate::proof::{prove_jwt, verify_jwt, Backend, Ipa, Kzg, ProvingConfig};
+
+    use halo2_proofs::SerdeFormat;
+
+    #[test]
+    fn keys_round_trip() {
+        let jwt = "{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\"}";
+        let credential = "twitter|337834122";
+        let k = 17;
+        let config = ProvingConfig::NATIVE;
+
+        let circuit = JwtCircuit::new(PreComputed::new(jwt, credential));
+        let params = Kzg::setup(k);
+        let pk = Kzg::keygen(&params, &circuit).unwrap();
+
+        let dir = std::env::temp_dir();
+        for format in [SerdeFormat::Processed, SerdeFormat::RawBytes] {
+            let vk_path = dir.join(format!("halo2_jwt_keys_round_trip_{:?}.vk", format));
+            let pk_path = dir.join(format!("halo2_jwt_keys_round_trip_{:?}.pk", format));
+
+            save_vk::<Kzg>(&vk_path, &circuit.params(k), pk.get_vk(), format).unwrap();
+            save_pk::<Kzg>(&pk_path, &circuit.params(k), &pk, format).unwrap();
+
+            let (vk_params, vk) = load_vk::<Kzg>(&vk_path).unwrap();
+            let (pk_params, loaded_pk) = load_pk::<Kzg>(&pk_path).unwrap();
+            assert_eq!(vk_params, circuit.params(k));
+            assert_eq!(pk_params, circuit.params(k));
+            assert_eq!(vk_hash(&vk), vk_hash(pk.get_vk()));
+
+            // A key of one backend is not read as a key of another
+            assert!(load_vk::<Ipa>(&vk_path).is_err());
+            // Nor a proving key as a verifying key
+            assert!(load_vk::<Kzg>(&pk_path).is_err());
+
+            // Proofs of the loaded proving key verify with the loaded verifying key alone
+            let proof = prove_jwt::<Kzg>(&params, &loaded_pk, circuit.clone(), &config).unwrap();
+            let bundle = JwtProof::new::<Kzg>(k, &config, &circuit, &vk, proof);
+            assert!(verify_jwt::<Kzg>(&params, &vk, &vk_params, &config, &bundle));
+
+            std::fs::remove_file(vk_path).unwrap();
+            std::fs::remove_file(pk_path).unwrap();
+        }
+    }
+}
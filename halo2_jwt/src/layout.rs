@@ -0,0 +1,231 @@
+//! Dry runs of a circuit layout, to pick the smallest `k` a circuit fits in.
+//!
+//! [`measure`] synthesizes a circuit with its floor planner into an [`Assignment`] that
+//! only records which rows are used, so it neither needs nor checks a witness.
+//! [`measure_regions`] also reports the rows of every region.
+
+use std::fmt;
+
+use ff::Field;
+use halo2_proofs::{
+    circuit::Value,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error, FloorPlanner, Fixed,
+        Instance, Selector,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// What a circuit uses of the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitDimensions {
+    /// Rows assigned in any column, lookup tables included.
+    pub rows: usize,
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    pub selectors: usize,
+    pub lookups: usize,
+    /// Rows reserved at the end of the table for zero knowledge.
+    pub blinding_factors: usize,
+    /// Rows the constraint system needs whatever the circuit assigns.
+    pub minimum_rows: usize,
+}
+
+impl CircuitDimensions {
+    /// The smallest `k` such that `2^k` rows hold the assigned and the blinding rows.
+    pub fn min_k(&self) -> u32 {
+        let needed = (self.rows + self.blinding_factors + 1).max(self.minimum_rows);
+        needed.next_power_of_two().trailing_zeros()
+    }
+}
+
+impl fmt::Display for CircuitDimensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} rows (k = {}), {} advice, {} fixed and {} instance columns, {} selectors, {} lookups",
+            self.rows,
+            self.min_k(),
+            self.advice_columns,
+            self.fixed_columns,
+            self.instance_columns,
+            self.selectors,
+            self.lookups,
+        )
+    }
+}
+
+/// The rows spanned by a region of the layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionRows {
+    /// The namespaces the region was assigned in, outermost first.
+    pub namespace: Vec<String>,
+    pub name: String,
+    pub rows: usize,
+}
+
+/// Lays out `circuit` and measures it.
+pub fn measure<F: Field, C: Circuit<F>>(circuit: &C) -> Result<CircuitDimensions, Error> {
+    Ok(measure_regions(circuit)?.0)
+}
+
+/// Lays out `circuit` and measures it, region by region in assignment order.
+pub fn measure_regions<F: Field, C: Circuit<F>>(circuit: &C) -> Result<(CircuitDimensions, Vec<RegionRows>), Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+
+    let mut measurer = Measurer::default();
+    C::FloorPlanner::synthesize(&mut measurer, circuit, config, cs.constants().clone())?;
+
+    let dimensions = CircuitDimensions {
+        rows: measurer.rows,
+        advice_columns: cs.num_advice_columns(),
+        fixed_columns: cs.num_fixed_columns(),
+        instance_columns: cs.num_instance_columns(),
+        selectors: cs.num_selectors(),
+        lookups: cs.lookups().len(),
+        blinding_factors: cs.blinding_factors(),
+        minimum_rows: cs.minimum_rows(),
+    };
+    Ok((dimensions, measurer.regions))
+}
+
+#[derive(Default)]
+struct Measurer {
+    rows: usize,
+    namespace: Vec<String>,
+    /// The current region, with the first and last rows it used so far.
+    region: Option<(String, Option<(usize, usize)>)>,
+    regions: Vec<RegionRows>,
+}
+
+impl Measurer {
+    fn use_row(&mut self, row: usize) {
+        self.rows = self.rows.max(row + 1);
+        if let Some((_, used)) = &mut self.region {
+            *used = Some(match *used {
+                Some((first, last)) => (first.min(row), last.max(row)),
+                None => (row, row),
+            });
+        }
+    }
+}
+
+impl<F: Field> Assignment<F> for Measurer {
+    fn enter_region<NR, N>(&mut self, name: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.region = Some((name().into(), None));
+    }
+
+    fn exit_region(&mut self) {
+        if let Some((name, used)) = self.region.take() {
+            self.regions.push(RegionRows {
+                namespace: self.namespace.clone(),
+                name,
+                rows: used.map_or(0, |(first, last)| last - first + 1),
+            });
+        }
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(&mut self, _: A, _: Column<Advice>, row: usize, _: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(&mut self, _: A, _: Column<Fixed>, row: usize, _: V) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // Fills the rest of the column, whatever its length
+    fn fill_from_row(&mut self, _: Column<Fixed>, _: usize, _: Value<Assigned<F>>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_challenge(&self, _: Challenge) -> Value<F> {
+        Value::unknown()
+    }
+
+    fn push_namespace<NR, N>(&mut self, name: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.namespace.push(name().into());
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        self.namespace.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::measure;
+    use crate::circuit::{JwtCircuit, JwtCircuitParams};
+    use crate::precompute::PreComputed;
+
+    use halo2_proofs::{dev::MockProver, plonk::Error};
+    use halo2curves::bn256::Fr;
+
+    #[test]
+    fn min_k_is_tight() {
+        let jwt = "{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\"}";
+        let credential = "twitter|337834122";
+        let circuit = JwtCircuit::new(PreComputed::new(jwt, credential));
+
+        let dimensions = measure::<Fr, _>(&circuit).unwrap();
+        let k = dimensions.min_k();
+        assert_eq!(circuit.min_params().unwrap().k, k);
+        // The spread table of SHA-256 does not dictate k
+        assert!(k < 17);
+
+        MockProver::run(k, &circuit, vec![circuit.public_inputs::<Fr>()])
+            .unwrap()
+            .assert_satisfied();
+        assert!(matches!(
+            MockProver::run(k - 1, &circuit, vec![circuit.public_inputs::<Fr>()]),
+            Err(Error::NotEnoughRowsAvailable { .. })
+        ));
+
+        // A dummy token of the same shape lays out the same
+        let params = circuit.params(k);
+        assert_eq!(params.dry_run().unwrap(), dimensions);
+        assert_eq!(params.with_min_k(), Some(params));
+        // Verifying a signature takes more rows
+        assert!(JwtCircuitParams { ps256: true, ..params }.with_min_k().unwrap().k > k);
+    }
+}
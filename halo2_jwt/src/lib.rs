@@ -1,6 +1,22 @@
 pub mod circuit;
+pub mod bytes;
+pub mod layout;
+pub mod cost;
 mod util;
 pub mod precompute;
-mod sha256;
+pub mod secret;
+pub mod sha256;
+pub mod rsa;
+pub mod pss;
+pub mod proof;
+pub mod bundle;
+pub mod keys;
+pub mod verifier;
+pub mod error;
+pub mod batch;
+pub mod aggregation;
 
-mod aggregation_ext;
\ No newline at end of file
+mod aggregation_ext;
+
+#[cfg(test)]
+mod fixtures;
@@ -1,28 +1,39 @@
 use std::path::Path;
 
+use halo2_jwt::aggregation::circuit::JwtAggregationCircuit;
+use halo2_jwt::aggregation::JwtAggregator;
 use halo2_jwt::circuit::JwtCircuit;
+use halo2_jwt::layout::measure;
 use halo2_jwt::precompute::PreComputed;
+use halo2_jwt::keys::save_vk;
+use halo2_jwt::proof::{gen_snark, Kzg, ProvingConfig};
 
 use halo2_proofs::dev::MockProver;
+use halo2_proofs::SerdeFormat;
+use halo2curves::bn256::Fr;
 
-use snark_verifier_sdk::{SHPLONK, gen_pk};
-use snark_verifier_sdk::halo2::aggregation::AggregationCircuit;
-use snark_verifier_sdk::halo2::{gen_srs, gen_snark_shplonk};
+use snark_verifier_sdk::gen_pk;
+use snark_verifier_sdk::halo2::gen_srs;
 use ark_std::{end_timer, start_timer};
 
 fn main() {
     env_logger::init(); 
-    let k_app = 17;
-    let k_agg = 22;
+    // the aggregation circuit below verifies SHPLONK snarks with a Poseidon transcript
+    let config = ProvingConfig::RECURSIVE;
 
     /* START: Setup the circuit & local pre-computed data */
     let jwt = "{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\",\"iat\":1639173028,\"exp\":1639209028,\"nonce\":\"44017a89\"}";
     let credential = "twitter|337834122";
 
     let precomputed = PreComputed::new(jwt, credential);
-    let public_inputs = precomputed.public_inputs();
+    let public_inputs = precomputed.public_inputs::<Fr>();
     let circuit = JwtCircuit::new(precomputed);
 
+    // the smallest k the token fits in
+    let dimensions = circuit.dimensions().expect("Circuit Layout Failed");
+    let k_app = dimensions.min_k();
+    log::info!("JWT circuit uses {}", dimensions);
+
     // pre-run check
     MockProver::run(k_app, &circuit.clone(), vec![public_inputs])
         .expect("Circuit Construction Failed")
@@ -33,31 +44,44 @@ fn main() {
     /* START: Setup SRS + Generate pk & vk */
     // 0. output paths
     let app_pk_path = Path::new("./artifacts/app.pk");
+    let app_vk_path = Path::new("./artifacts/app.vk");
     let app_snark_path = Path::new("./artifacts/app.snark");
     let agg_pk_path = Path::new("./artifacts/agg.pk");
     let agg_snark_path = Path::new("./artifacts/agg.snark");
 
     // 1. generate params
     let params_app = gen_srs(k_app);
-    let params_agg = gen_srs(k_agg);
     log::info!("SRS Parameter generated or readed");
 
     // 2. generate application pk & snark
     let app_snark_gen_timer = start_timer!(|| "app_snark_gen");
     let pk_app = gen_pk(&params_app, &circuit, Some(app_pk_path));
-    let snark_app = gen_snark_shplonk(&params_app, &pk_app, circuit, Some(app_snark_path));
+    // the verifying key alone, with the circuit params, for verifiers
+    save_vk::<Kzg>(app_vk_path, &circuit.params(k_app), pk_app.get_vk(), SerdeFormat::RawBytes)
+        .expect("Failed to write the application vk");
+    let snark_app = gen_snark(&params_app, &pk_app, circuit, Some(app_snark_path), &config)
+        .expect("Application snark generation failed");
     end_timer!(app_snark_gen_timer);
     log::info!("Application pk & snark generated");
 
     // 3. generate aggreation pk & snark
-    let aggregation_circuit = AggregationCircuit::<SHPLONK>::new(&params_agg, vec![snark_app]);
-    
+    // the application snark doubles as the dummy filling free aggregation slots
     let agg_snark_gen_timer = start_timer!(|| "agg_snark_gen");
-    let agg_pk = gen_pk(&params_agg, &aggregation_circuit, Some(agg_pk_path));
-    
+    // the layout does not depend on the SRS, so the application one measures it
+    let agg_circuit = JwtAggregationCircuit::new(&params_app, vec![snark_app.clone()])
+        .expect("Application snark rejected");
+    let agg_dimensions = measure::<Fr, _>(&agg_circuit).expect("Aggregation Circuit Layout Failed");
+    let k_agg = agg_dimensions.min_k();
+    log::info!("Aggregation circuit uses {}", agg_dimensions);
+    let params_agg = gen_srs(k_agg);
+    let aggregator = JwtAggregator::new(params_agg, 1, snark_app.clone(), Some(agg_pk_path))
+        .expect("Aggregation keygen failed");
+
     // remove previously generated snark - let's start fresh
     std::fs::remove_file(agg_snark_path).unwrap_or_default();
-    let _agg_snark = gen_snark_shplonk(&params_agg, &agg_pk, aggregation_circuit, Some(agg_snark_path));
+    let _aggregated = aggregator
+        .aggregate(vec![snark_app], Some(agg_snark_path))
+        .expect("Aggregation failed");
     end_timer!(agg_snark_gen_timer);
     log::info!("Aggregation pk & snark generated");
     /* END: Setup SRS + Generate pk & vk */
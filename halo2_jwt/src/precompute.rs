@@ -1,18 +1,24 @@
-use halo2curves::bn256::Fr;
+use std::fmt;
 
+use ff::PrimeField;
+use zeroize::Zeroizing;
+
+use crate::secret::{debug_witness, SecretBytes};
 use crate::sha256::BlockWord;
-use crate::util::{find_subsequence_u8, pad_bytes_front_n_end, sha256_hash_bytes_digests, pad_sha256_bytes, bytes_to_u32_array, u32_array_to_blockwords};
+use crate::util::{find_subsequence_u8, sha256_hash_bytes_digests, pad_sha256_bytes, bytes_to_u32_array, u32_array_to_blockwords};
 
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+/// Off-circuit witness of a JWT and the credential it contains. Its `Debug` output is
+/// redacted, see [`crate::secret`].
+#[derive(Clone, PartialEq, Eq, Default)]
 pub struct PreComputed {
-    jwt_bytes: Vec<u8>,
-    original_credential_bytes: Vec<u8>,
-    credential_bytes: Vec<u8>,
-
-    segment_location_start: usize,
-    segment_location_end: usize,
-    segment_start_offset: usize,
-    segment_end_offset: usize,
+    jwt_bytes: SecretBytes,
+    credential_bytes: SecretBytes,
+    /// Byte position of the credential within the JWT, a private witness.
+    credential_position: usize,
+    /// SHA-256 padding of the JWT and of the credential. The circuit assigns it as
+    /// constants, so the verifying key fixes it from the lengths.
+    jwt_padding: Vec<u8>,
+    credential_padding: Vec<u8>,
 
     digest_jwt: [u8; 32],
     digest_credential: [u8; 32],
@@ -27,37 +33,30 @@ impl PreComputed {
     }
 
     pub fn new_with_bytes(jwt: &[u8], credential: &[u8]) -> Self {
-        let credential_len = credential.len();
-
-        let byte_position = find_subsequence_u8(jwt, credential)
+        let credential_position = find_subsequence_u8(jwt, credential)
             .expect("credential to be contained within the JWT");
 
-        let segment_location_start = byte_position / 4;
-        let segment_location_end = (byte_position + credential_len) / 4;
-        let segment_start_offset = byte_position % 4;
-        let segment_end_offset = (byte_position + credential_len) % 4;
-
-        let padded_credential = pad_bytes_front_n_end(credential, segment_start_offset, segment_end_offset);
-
         Self {
-            jwt_bytes: jwt.to_vec(),
-            original_credential_bytes: credential.to_vec(),
-            credential_bytes: padded_credential.to_vec(),
-
-            segment_location_start, segment_location_end,
-            segment_start_offset, segment_end_offset,
+            jwt_bytes: jwt.into(),
+            credential_bytes: credential.into(),
+            credential_position,
+            jwt_padding: sha256_padding(jwt),
+            credential_padding: sha256_padding(credential),
 
             digest_jwt: sha256_hash_bytes_digests(&jwt),
-            digest_credential: sha256_hash_bytes_digests(&padded_credential),
+            // The credential alone, so a verifier can recompute it from an identifier
+            // without knowing where it sits in the token
+            digest_credential: sha256_hash_bytes_digests(credential),
         }
     }
 
+    /// The SHA-256 padded JWT and credential, word by word. The words are copies handed
+    /// to the circuit, and are not wiped.
     pub fn preimage_as_blockwords(&self) -> [Vec<BlockWord>; 2] {
-        let padded_message_jwt = pad_sha256_bytes(&self.jwt_bytes);
-        let padded_message_credential = pad_sha256_bytes(&self.credential_bytes);
+        let [padded_message_jwt, padded_message_credential] = self.preimage_as_bytes();
 
-        let u32_padded_jwt = bytes_to_u32_array(&padded_message_jwt);
-        let u32_padded_credential = bytes_to_u32_array(&padded_message_credential);
+        let u32_padded_jwt = Zeroizing::new(bytes_to_u32_array(&padded_message_jwt));
+        let u32_padded_credential = Zeroizing::new(bytes_to_u32_array(&padded_message_credential));
 
         [
             u32_array_to_blockwords(&u32_padded_jwt),
@@ -65,6 +64,14 @@ impl PreComputed {
         ]
     }
 
+    /// The SHA-256 padded JWT and credential, byte by byte.
+    pub fn preimage_as_bytes(&self) -> [SecretBytes; 2] {
+        [
+            [self.jwt_bytes.expose(), &self.jwt_padding[..]].concat().into(),
+            [self.credential_bytes.expose(), &self.credential_padding[..]].concat().into(),
+        ]
+    }
+
     pub fn expected_digest_as_blockwords(&self) -> [Vec<BlockWord>; 2] {
         let digest_jwt = bytes_to_u32_array(&self.digest_jwt);
         let digest_credential = bytes_to_u32_array(&self.digest_credential);
@@ -75,38 +82,62 @@ impl PreComputed {
         ]
     }
 
-    pub fn public_inputs(&self) -> Vec<Fr> {
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<F> {
         let mut result = Vec::with_capacity(16);
         let digest_jwt_u32 = bytes_to_u32_array(&self.digest_jwt);
         let digest_credential_u32 = bytes_to_u32_array(&self.digest_credential);
 
         for i in 0..8 {
-            result.push(Fr::from(digest_jwt_u32[i] as u64));
+            result.push(F::from(digest_jwt_u32[i] as u64));
         }
 
         for i in 0..8 {
-            result.push(Fr::from(digest_credential_u32[i] as u64));
+            result.push(F::from(digest_credential_u32[i] as u64));
         }
 
-        result.push(Fr::from(self.segment_start_offset as u64));
-        result.push(Fr::from(self.segment_end_offset as u64));
-
         result
     }
     
-    pub fn segment_location(&self) -> (usize, usize) {
-        (self.segment_location_start, self.segment_location_end)
+    pub fn jwt_len(&self) -> usize {
+        self.jwt_bytes.len()
+    }
+
+    pub fn credential_len(&self) -> usize {
+        self.credential_bytes.len()
     }
 
-    pub fn segment_offset(&self) -> (usize, usize) {
-        (self.segment_start_offset, self.segment_end_offset)
+    /// Byte position of the credential within the JWT.
+    pub fn credential_position(&self) -> usize {
+        self.credential_position
     }
 
     pub fn log_all(&self) {
-        log::info!("[Pre-Constrained] JWT Len: {:?} Credential Len: {:?} Credential Padded Len {:?}", self.jwt_bytes.len(), self.original_credential_bytes.len(), self.credential_bytes.len());
-        log::debug!("[Pre-Constrained] JWT Hash: {:?} Credential Hash: {:?}", self.digest_jwt, self.digest_credential);
-        log::info!("[Pre-Constrained] Segment Start: {:?} Segment End: {:?}", self.segment_location_start, self.segment_location_end);
-        log::info!("[Pre-Constrained] Segment Start Offset: {:?} Segment End Offset: {:?}", self.segment_start_offset, self.segment_end_offset);
+        log::info!("[Pre-Constrained] JWT Len: {:?} Credential Len: {:?}", self.jwt_bytes.len(), self.credential_bytes.len());
+        debug_witness!("[Pre-Constrained] JWT Hash: {:?} Credential Hash: {:?}", self.digest_jwt, self.digest_credential);
+        debug_witness!("[Pre-Constrained] Credential Position: {:?}", self.credential_position);
+    }
+}
+
+/// The bytes SHA-256 appends to `message`.
+fn sha256_padding(message: &[u8]) -> Vec<u8> {
+    pad_sha256_bytes(message).split_off(message.len())
+}
+
+impl fmt::Debug for PreComputed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("PreComputed");
+        debug
+            .field("jwt_bytes", &self.jwt_bytes)
+            .field("credential_bytes", &self.credential_bytes);
+
+        // Where the credential sits tells about the token
+        #[cfg(feature = "unsafe-debug")]
+        debug
+            .field("credential_position", &self.credential_position)
+            .field("digest_jwt", &self.digest_jwt)
+            .field("digest_credential", &self.digest_credential);
+
+        debug.finish_non_exhaustive()
     }
 }
 
@@ -126,5 +157,361 @@ fn e2e_precompute_test() {
     ];
     let precompute = PreComputed::new_with_bytes(&a, &b);
 
-    println!("{:?}", precompute.credential_bytes);
+    assert_eq!(precompute.credential_position(), 2);
+    assert_eq!(precompute.credential_bytes.expose(), &b[..]);
+    assert_eq!(precompute.digest_credential, sha256_hash_bytes_digests(&b));
+}
+
+/// A prover controls the whole witness, so most of these tamper with it directly and
+/// check the circuit rejects every claim the witness does not back.
+#[cfg(test)]
+mod tests {
+    use super::PreComputed;
+    use crate::bundle::vk_hash;
+    use crate::circuit::JwtCircuit;
+    use crate::proof::{Backend, Kzg, ProvingConfig};
+    use crate::sha256::{
+        Sha256Instructions, Table16Chip, Table16Config, BLOCK_SIZE, BLOCK_SIZE_BYTES,
+    };
+    use crate::util::{find_subsequence_u8, pad_sha256_bytes, sha256_hash_bytes_digests};
+
+    use ff::Field;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{keygen_vk, Circuit, ConstraintSystem, Error},
+    };
+    use halo2curves::bn256::Fr;
+    use std::convert::TryInto;
+    use proptest::{prelude::*, sample::Index};
+    use sha2::{Digest, Sha256};
+
+    const JWT: &str = "{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\"}";
+    const CREDENTIAL: &str = "twitter|337834122";
+    const K: u32 = 17;
+
+    fn honest() -> PreComputed {
+        PreComputed::new(JWT, CREDENTIAL)
+    }
+
+    fn satisfied_with(precomputed: PreComputed, public_inputs: Vec<Fr>) -> bool {
+        MockProver::run(K, &JwtCircuit::new(precomputed), vec![public_inputs])
+            .unwrap()
+            .verify()
+            .is_ok()
+    }
+
+    /// Whether the circuit is satisfied with the claims of the witness as public inputs.
+    fn satisfied(precomputed: PreComputed) -> bool {
+        let public_inputs = precomputed.public_inputs();
+        satisfied_with(precomputed, public_inputs)
+    }
+
+    /// Claims another credential at the same place, with a consistent digest.
+    fn with_credential(precomputed: &PreComputed, credential: &[u8]) -> PreComputed {
+        PreComputed {
+            credential_bytes: credential.into(),
+            digest_credential: sha256_hash_bytes_digests(credential),
+            ..precomputed.clone()
+        }
+    }
+
+    #[test]
+    fn honest_witness_is_accepted() {
+        let precomputed = honest();
+        // The cases below rely on the credential straddling words on both ends
+        let position = precomputed.credential_position();
+        assert_eq!((position % 4, (position + precomputed.credential_len()) % 4), (2, 3));
+        assert!(satisfied(precomputed));
+    }
+
+    #[cfg(not(feature = "unsafe-debug"))]
+    #[test]
+    fn debug_is_redacted() {
+        let debug = format!("{:?}", JwtCircuit::new(honest()));
+        assert!(!debug.contains("twitter"));
+        assert!(!debug.contains(&format!("{:?}", CREDENTIAL.as_bytes())[1..20]));
+        assert!(debug.contains("<17 bytes redacted>"));
+    }
+
+    #[test]
+    fn wrong_jwt_digest_is_rejected() {
+        let mut precomputed = honest();
+        precomputed.digest_jwt[31] ^= 1;
+        assert!(!satisfied(precomputed));
+    }
+
+    #[test]
+    fn wrong_credential_digest_is_rejected() {
+        let mut precomputed = honest();
+        precomputed.digest_credential[0] ^= 0x80;
+        assert!(!satisfied(precomputed));
+    }
+
+    #[test]
+    fn public_inputs_other_than_the_witness_are_rejected() {
+        let public_inputs = honest().public_inputs::<Fr>();
+        for index in [0, 7, 8, 15] {
+            let mut tampered = public_inputs.clone();
+            tampered[index] += Fr::ONE;
+            assert!(!satisfied_with(honest(), tampered), "public input {} not bound", index);
+        }
+    }
+
+    #[test]
+    fn shifted_credential_is_rejected() {
+        // By a byte, within the word, and by a whole word
+        let precomputed = honest();
+        let position = precomputed.credential_position();
+        for shift in [-4isize, -1, 1, 4] {
+            let shifted = PreComputed {
+                credential_position: (position as isize + shift) as usize,
+                ..precomputed.clone()
+            };
+            assert!(!satisfied(shifted), "credential shifted by {} bytes", shift);
+        }
+    }
+
+    #[test]
+    fn position_past_the_last_start_is_rejected() {
+        // Past the last start, where the shifted bytes run off the end of the JWT
+        let precomputed = honest();
+        for position in [precomputed.jwt_len() - precomputed.credential_len() + 1, precomputed.jwt_len()] {
+            let tampered = PreComputed {
+                credential_position: position,
+                ..precomputed.clone()
+            };
+            assert!(!satisfied(tampered), "credential at {}", position);
+        }
+    }
+
+    #[test]
+    fn preimage_not_padded_by_sha256_is_rejected() {
+        // Claims the 0x80 of the padding as a byte of the JWT and pads with the rest, so
+        // the blocks are the honest ones and so is the digest. Only the padding the
+        // verifying key fixes for the claimed length tells them apart.
+        let precomputed = honest();
+        let mut claimed = precomputed.jwt_bytes.to_vec();
+        claimed.push(0x80);
+        let tampered = PreComputed {
+            jwt_bytes: claimed.clone().into(),
+            jwt_padding: precomputed.jwt_padding[1..].to_vec(),
+            ..precomputed.clone()
+        };
+        assert_eq!(tampered.preimage_as_bytes()[0], precomputed.preimage_as_bytes()[0]);
+        assert!(satisfied_with(tampered.clone(), precomputed.public_inputs()));
+
+        let padded = JwtCircuit::new(PreComputed::new_with_bytes(&claimed, CREDENTIAL.as_bytes()));
+        let params = padded.min_params().unwrap();
+        assert_eq!(params, JwtCircuit::new(tampered.clone()).min_params().unwrap());
+
+        let setup = Kzg::setup(params.k);
+        let pk = Kzg::keygen(&setup, &padded).unwrap();
+        let instances = vec![precomputed.public_inputs()];
+        let config = ProvingConfig::NATIVE;
+        let proof = Kzg::prove(&setup, &pk, JwtCircuit::new(tampered), &instances, &config);
+        assert!(!proof.map_or(false, |proof| Kzg::verify(&setup, pk.get_vk(), &instances, &proof, &config)));
+    }
+
+    /// Hashes the JWT with the SHA-256 chip alone, witnessing one word of the
+    /// feed-forward of the last block off by one.
+    struct WrongSum {
+        precomputed: PreComputed,
+        word: Option<usize>,
+    }
+
+    impl Circuit<Fr> for WrongSum {
+        type Config = Table16Config<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            WrongSum {
+                precomputed: self.precomputed.clone(),
+                word: self.word,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let [blocks, _] = self.precomputed.preimage_as_blockwords();
+            let last = blocks.len() / BLOCK_SIZE - 1;
+            let mut state = chip.initialization_vector(&mut layouter)?;
+            for (idx, block) in blocks.chunks(BLOCK_SIZE).enumerate() {
+                if idx > 0 {
+                    state = chip.initialization(&mut layouter, &state)?;
+                }
+                let block = block.try_into().unwrap();
+                state = match self.word {
+                    Some(word) if idx == last => {
+                        chip.compress_with_wrong_sum(&mut layouter, &state, block, word)?
+                    }
+                    _ => chip.compress(&mut layouter, &state, block)?,
+                };
+            }
+            chip.digest(&mut layouter, &state)?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn wrong_feed_forward_sum_is_rejected() {
+        let precomputed = honest();
+        assert!(precomputed.jwt_len() > BLOCK_SIZE_BYTES);
+
+        for word in [None, Some(0), Some(3), Some(7)] {
+            let circuit = WrongSum {
+                precomputed: precomputed.clone(),
+                word,
+            };
+            let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify().is_ok(), word.is_none(), "word {:?}", word);
+        }
+    }
+
+    #[test]
+    fn altered_boundary_bytes_are_rejected() {
+        let precomputed = honest();
+        let end = precomputed.credential_position() + precomputed.credential_len();
+        let first_of_last_word = end / 4 * 4 - precomputed.credential_position();
+
+        // The first byte of the credential, of a word, and of the last word
+        for index in [0, 2, first_of_last_word] {
+            let mut credential = CREDENTIAL.as_bytes().to_vec();
+            credential[index] ^= 1;
+            assert!(
+                !satisfied(with_credential(&precomputed, &credential)),
+                "credential byte {} not bound",
+                index
+            );
+        }
+    }
+
+    /// A JWT with `credential` at byte `position`, followed by `suffix` more bytes.
+    fn token(position: usize, credential: &[u8], suffix: usize) -> Vec<u8> {
+        let mut jwt = vec![b'.'; position];
+        jwt.extend_from_slice(credential);
+        jwt.extend(std::iter::repeat(b'.').take(suffix));
+        jwt
+    }
+
+    /// Like [`satisfied`], in as few rows as the circuit fits in.
+    fn satisfied_at_min_k(precomputed: PreComputed) -> bool {
+        let circuit = JwtCircuit::new(precomputed);
+        let k = circuit.min_params().unwrap().k;
+        MockProver::run(k, &circuit, vec![circuit.public_inputs::<Fr>()])
+            .unwrap()
+            .verify()
+            .is_ok()
+    }
+
+    #[test]
+    fn position_is_not_in_the_verifying_key() {
+        // Tokens of the same length share their params and keys, wherever the credential
+        let credential = CREDENTIAL.as_bytes();
+        let first = JwtCircuit::new(PreComputed::new_with_bytes(&token(0, credential, 9), credential));
+        let last = JwtCircuit::new(PreComputed::new_with_bytes(&token(9, credential, 0), credential));
+        let params = first.min_params().unwrap();
+        assert_eq!(params, last.min_params().unwrap());
+
+        let setup = Kzg::setup(params.k);
+        let key = |circuit: &JwtCircuit| vk_hash(&keygen_vk(&setup, circuit).unwrap());
+        assert_eq!(key(&first), key(&last));
+    }
+
+    #[test]
+    fn every_alignment_is_proven() {
+        // Every start offset with every end offset, from credentials within a single
+        // word to ones spanning three, in the middle and at the very end of the JWT
+        for start_offset in 0..4 {
+            for len in 1..=6 {
+                for suffix in [0, 5] {
+                    let credential = &b"abcdef"[..len];
+                    let precomputed = PreComputed::new_with_bytes(&token(4 + start_offset, credential, suffix), credential);
+                    assert_eq!(precomputed.credential_position(), 4 + start_offset);
+
+                    // The public inputs do not depend on where the credential sits
+                    let elsewhere = PreComputed::new_with_bytes(&token(0, credential, 7), credential);
+                    assert_eq!(&precomputed.public_inputs::<Fr>()[8..], &elsewhere.public_inputs::<Fr>()[8..]);
+
+                    let case = format!("start offset {}, length {}, suffix {}", start_offset, len, suffix);
+                    assert!(satisfied_at_min_k(precomputed.clone()), "{} not proven", case);
+                    if suffix == 0 {
+                        continue;
+                    }
+
+                    for index in [0, len - 1] {
+                        let mut altered = credential.to_vec();
+                        altered[index] ^= 1;
+                        assert!(
+                            !satisfied_at_min_k(with_credential(&precomputed, &altered)),
+                            "{}: byte {} not bound",
+                            case,
+                            index
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// JWTs of any length up to a few blocks, with extra weight on the lengths around
+    /// block boundaries.
+    fn jwt() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            prop::collection::vec(any::<u8>(), 1..200),
+            prop::sample::select(vec![55usize, 56, 57, 63, 64, 65, 119, 120, 127, 128])
+                .prop_flat_map(|len| prop::collection::vec(any::<u8>(), len)),
+        ]
+    }
+
+    fn words(digest: &[u8]) -> Vec<Fr> {
+        digest
+            .chunks(4)
+            .map(|word| Fr::from(u32::from_be_bytes(word.try_into().unwrap()) as u64))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn precomputed_matches_references(jwt in jwt(), start in any::<Index>(), len in any::<Index>()) {
+            let start = start.index(jwt.len());
+            let credential = jwt[start..=start + len.index(jwt.len() - start)].to_vec();
+            let precomputed = PreComputed::new_with_bytes(&jwt, &credential);
+
+            // the first occurrence, which may come before `start`
+            let position = find_subsequence_u8(&jwt, &credential).unwrap();
+            prop_assert_eq!(precomputed.jwt_len(), jwt.len());
+            prop_assert_eq!(precomputed.credential_len(), credential.len());
+            prop_assert_eq!(precomputed.credential_position(), position);
+
+            prop_assert_eq!(precomputed.credential_bytes.expose(), &credential[..]);
+            prop_assert_eq!(&precomputed.digest_jwt[..], &Sha256::digest(&jwt)[..]);
+            prop_assert_eq!(&precomputed.digest_credential[..], &Sha256::digest(&credential)[..]);
+
+            let [jwt_preimage, credential_preimage] = precomputed.preimage_as_bytes();
+            prop_assert_eq!(jwt_preimage.expose(), &pad_sha256_bytes(&jwt)[..]);
+            prop_assert_eq!(credential_preimage.expose(), &pad_sha256_bytes(&credential)[..]);
+            let [jwt_words, credential_words] = precomputed.preimage_as_blockwords();
+            prop_assert_eq!(jwt_words.len(), jwt_preimage.len() / 4);
+            prop_assert_eq!(credential_words.len(), credential_preimage.len() / 4);
+
+            let public_inputs = precomputed.public_inputs::<Fr>();
+            prop_assert_eq!(public_inputs.len(), 16);
+            prop_assert_eq!(&public_inputs[..8], &words(&Sha256::digest(&jwt))[..]);
+            prop_assert_eq!(&public_inputs[8..], &words(&Sha256::digest(&credential))[..]);
+        }
+    }
 }
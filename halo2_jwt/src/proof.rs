@@ -1,32 +1,125 @@
-use halo2curves::bn256::{Bn256, Fq, Fr, G1Affine};
+use std::{
+    io::{self, Read, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use ff::{FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
+use halo2curves::{
+    bn256::{Bn256, Fq, Fr, G1Affine, G2Affine},
+    pasta::{EqAffine, Fp},
+    CurveAffine,
+};
 use rand::rngs::OsRng;
 
 use halo2_proofs::{
     dev::MockProver,
     plonk::{
-        create_proof, keygen_pk, keygen_vk, verify_proof, Circuit,
+        create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, Error,
         ProvingKey, VerifyingKey,
     },
     poly::{
-        commitment::{Params, ParamsProver},
+        commitment::{CommitmentScheme, Params, ParamsProver, Prover, Verifier},
+        ipa::{
+            commitment::{IPACommitmentScheme, ParamsIPA},
+            multiopen::{ProverIPA, VerifierIPA},
+            strategy::SingleStrategy as IpaStrategy,
+        },
         kzg::{
             commitment::{KZGCommitmentScheme, ParamsKZG},
-            multiopen::{ProverGWC, VerifierGWC},
-            strategy::AccumulatorStrategy,
+            multiopen::{ProverGWC, ProverSHPLONK, VerifierGWC, VerifierSHPLONK},
+            strategy::SingleStrategy as KzgStrategy,
         },
         VerificationStrategy,
     },
-    transcript::{TranscriptReadBuffer, TranscriptWriterBuffer},
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, TranscriptReadBuffer,
+        TranscriptWriterBuffer,
+    },
+    SerdeFormat,
 };
 use snark_verifier::{
-    loader::evm::{self, deploy_and_call, encode_calldata, EvmLoader},
-    pcs::kzg::{Gwc19, KzgAs},
+    loader::{
+        evm::{self, deploy_and_call, encode_calldata, EvmLoader},
+        native::NativeLoader,
+    },
+    pcs::kzg::{Bdfg21, Gwc19, KzgAs},
     system::halo2::{compile, transcript::evm::EvmTranscript, Config},
-    verifier::{self, SnarkVerifier},
+    verifier::{self, plonk::PlonkProtocol, SnarkVerifier},
+};
+use snark_verifier_sdk::{
+    halo2::{gen_snark_gwc, gen_snark_shplonk, PoseidonTranscript},
+    CircuitExt, Snark,
 };
-use std::rc::Rc;
 
-type PlonkVerifier = verifier::plonk::PlonkVerifier<KzgAs<Bn256, Gwc19>>;
+use crate::bundle::{circuit_params_hash, vk_hash, JwtProof};
+use crate::circuit::{JwtCircuit, JwtCircuitParams};
+
+type PlonkVerifier<MOS> = verifier::plonk::PlonkVerifier<KzgAs<Bn256, MOS>>;
+
+type Blake2bWriter<C> = Blake2bWrite<Vec<u8>, C, Challenge255<C>>;
+type Blake2bReader<'a, C> = Blake2bRead<&'a [u8], C, Challenge255<C>>;
+type EvmWriter = EvmTranscript<G1Affine, NativeLoader, Vec<u8>, Vec<u8>>;
+type EvmReader<'a> = EvmTranscript<G1Affine, NativeLoader, &'a [u8], Vec<u8>>;
+type PoseidonWriter = PoseidonTranscript<NativeLoader, Vec<u8>>;
+type PoseidonReader<'a> = PoseidonTranscript<NativeLoader, &'a [u8]>;
+
+/// How the polynomial openings of a KZG proof are batched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MultiOpen {
+    /// GWC19, the cheaper to verify on chain.
+    #[default]
+    Gwc,
+    /// SHPLONK (BDFG21), the cheaper to verify in an aggregation circuit.
+    Shplonk,
+}
+
+/// The hash the Fiat-Shamir challenges are drawn with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TranscriptKind {
+    /// Blake2b, the fastest to verify natively.
+    #[default]
+    Blake2b,
+    /// Keccak256, as read by the generated EVM verifier.
+    Evm,
+    /// Poseidon, the cheapest to verify inside another circuit.
+    Poseidon,
+}
+
+/// Selects the multi-open scheme and transcript of a proof. Prover and verifier must
+/// agree on it, and an aggregation circuit must match the snarks it verifies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProvingConfig {
+    pub multi_open: MultiOpen,
+    pub transcript: TranscriptKind,
+}
+
+impl ProvingConfig {
+    /// Proofs verified off chain.
+    pub const NATIVE: Self = Self {
+        multi_open: MultiOpen::Gwc,
+        transcript: TranscriptKind::Blake2b,
+    };
+
+    /// Proofs verified by the contract from [`gen_evm_verifier`].
+    pub const EVM: Self = Self {
+        multi_open: MultiOpen::Gwc,
+        transcript: TranscriptKind::Evm,
+    };
+
+    /// Proofs verified by an aggregation or recursive circuit.
+    pub const RECURSIVE: Self = Self {
+        multi_open: MultiOpen::Shplonk,
+        transcript: TranscriptKind::Poseidon,
+    };
+}
+
+fn unsupported(config: &ProvingConfig) -> Error {
+    Error::Transcript(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("unsupported proving config {:?}", config),
+    ))
+}
 
 pub fn gen_srs(k: u32) -> ParamsKZG<Bn256> {
     ParamsKZG::<Bn256>::setup(k, OsRng)
@@ -42,53 +135,67 @@ pub fn gen_proof<C: Circuit<Fr>>(
     pk: &ProvingKey<G1Affine>,
     circuit: C,
     instances: Vec<Vec<Fr>>,
+    config: &ProvingConfig,
 ) -> Vec<u8> {
     MockProver::run(params.k(), &circuit, instances.clone())
         .unwrap()
         .assert_satisfied();
 
-    let instances_ref = &instances
-        .iter()
-        .map(|instances| instances.as_slice())
-        .collect::<Vec<_>>();
-
-    let proof = {
-        let mut transcript = TranscriptWriterBuffer::<_, G1Affine, _>::init(Vec::new());
-        create_proof::<KZGCommitmentScheme<Bn256>, ProverGWC<_>, _, _, EvmTranscript<_, _, _, _>, _>(
-            params,
-            pk,
-            &[circuit],
-            &[instances_ref],
-            OsRng,
-            &mut transcript,
-        )
-        .unwrap();
-        transcript.finalize()
-    };
-
-    let accept = {
-        let mut transcript = TranscriptReadBuffer::<_, G1Affine, _>::init(proof.as_slice());
-        VerificationStrategy::<_, VerifierGWC<_>>::finalize(
-            verify_proof::<_, VerifierGWC<_>, _, EvmTranscript<_, _, _, _>, _>(
-                params.verifier_params(),
-                pk.get_vk(),
-                AccumulatorStrategy::new(params.verifier_params()),
-                &[instances_ref],
-                &mut transcript,
-            )
-            .unwrap(),
-        )
-    };
-    assert!(accept);
+    let proof = Kzg::prove(params, pk, circuit, &instances, config).unwrap();
+    assert!(Kzg::verify(params, pk.get_vk(), &instances, &proof, config));
 
     proof
 }
 
+/// Generates a snark for aggregation. The SDK always uses a Poseidon transcript, so
+/// any other config is unsupported.
+pub fn gen_snark<C: CircuitExt<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    path: Option<&Path>,
+    config: &ProvingConfig,
+) -> Result<Snark, Error> {
+    if config.transcript != TranscriptKind::Poseidon {
+        return Err(unsupported(config));
+    }
+    Ok(match config.multi_open {
+        MultiOpen::Gwc => gen_snark_gwc(params, pk, circuit, path),
+        MultiOpen::Shplonk => gen_snark_shplonk(params, pk, circuit, path),
+    })
+}
+
+/// Compiles the verifier contract of `vk`. It reads a Keccak256 transcript, so any
+/// other config is unsupported.
 pub fn gen_evm_verifier(
     params: &ParamsKZG<Bn256>,
     vk: &VerifyingKey<G1Affine>,
     num_instance: Vec<usize>,
-) -> Vec<u8> {
+    config: &ProvingConfig,
+) -> Result<Vec<u8>, Error> {
+    if config.transcript != TranscriptKind::Evm {
+        return Err(unsupported(config));
+    }
+    Ok(match config.multi_open {
+        MultiOpen::Gwc => compile_evm_verifier::<Gwc19>(params, vk, num_instance),
+        MultiOpen::Shplonk => compile_evm_verifier::<Bdfg21>(params, vk, num_instance),
+    })
+}
+
+fn compile_evm_verifier<MOS>(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    num_instance: Vec<usize>,
+) -> Vec<u8>
+where
+    PlonkVerifier<MOS>: SnarkVerifier<
+        G1Affine,
+        Rc<EvmLoader>,
+        Protocol = PlonkProtocol<G1Affine, Rc<EvmLoader>>,
+    >,
+    <PlonkVerifier<MOS> as SnarkVerifier<G1Affine, Rc<EvmLoader>>>::VerifyingKey:
+        From<(G1Affine, G2Affine, G2Affine)>,
+{
     let protocol = compile(
         params,
         vk,
@@ -101,17 +208,382 @@ pub fn gen_evm_verifier(
     let mut transcript = EvmTranscript::<_, Rc<EvmLoader>, _, _>::new(&loader);
 
     let instances = transcript.load_instances(num_instance);
-    let proof = PlonkVerifier::read_proof(&vk, &protocol, &instances, &mut transcript).unwrap();
-    PlonkVerifier::verify(&vk, &protocol, &instances, &proof).unwrap();
+    let proof = PlonkVerifier::<MOS>::read_proof(&vk, &protocol, &instances, &mut transcript).unwrap();
+    PlonkVerifier::<MOS>::verify(&vk, &protocol, &instances, &proof).unwrap();
 
-    println!("Yul Code {:?}", loader.yul_code());
+    log::debug!("Yul Code {:?}", loader.yul_code());
     evm::compile_yul(&loader.yul_code())
 }
 
 pub fn evm_verify(deployment_code: Vec<u8>, instances: Vec<Vec<Fr>>, proof: Vec<u8>) {
     let calldata = encode_calldata(&instances, &proof);
 
-    println!("calldata len {:?}", calldata.len());
+    log::debug!("calldata len {:?}", calldata.len());
     let gas_cost = deploy_and_call(deployment_code, calldata).unwrap();
-    dbg!(gas_cost);
+    log::debug!("gas cost {:?}", gas_cost);
+}
+
+/// A commitment scheme to prove and verify circuits with.
+pub trait Backend {
+    /// The field the circuit is synthesized over.
+    type Scalar: PrimeField;
+    /// The curve the commitments live on.
+    type Affine: CurveAffine<ScalarExt = Self::Scalar>;
+    /// The public parameters of the scheme.
+    type Params;
+    /// Identifies the backend in serialized proofs.
+    const NAME: &'static str;
+
+    fn setup(k: u32) -> Self::Params;
+
+    fn keygen<C: Circuit<Self::Scalar>>(
+        params: &Self::Params,
+        circuit: &C,
+    ) -> Result<ProvingKey<Self::Affine>, Error>;
+
+    fn prove<C: Circuit<Self::Scalar>>(
+        params: &Self::Params,
+        pk: &ProvingKey<Self::Affine>,
+        circuit: C,
+        instances: &[Vec<Self::Scalar>],
+        config: &ProvingConfig,
+    ) -> Result<Vec<u8>, Error>;
+
+    fn verify(
+        params: &Self::Params,
+        vk: &VerifyingKey<Self::Affine>,
+        instances: &[Vec<Self::Scalar>],
+        proof: &[u8],
+        config: &ProvingConfig,
+    ) -> bool;
+
+    fn write_vk<W: Write>(
+        vk: &VerifyingKey<Self::Affine>,
+        writer: &mut W,
+        format: SerdeFormat,
+    ) -> io::Result<()>;
+
+    fn read_vk<R: Read, C: Circuit<Self::Scalar>>(
+        reader: &mut R,
+        format: SerdeFormat,
+    ) -> io::Result<VerifyingKey<Self::Affine>>;
+
+    fn write_pk<W: Write>(
+        pk: &ProvingKey<Self::Affine>,
+        writer: &mut W,
+        format: SerdeFormat,
+    ) -> io::Result<()>;
+
+    fn read_pk<R: Read, C: Circuit<Self::Scalar>>(
+        reader: &mut R,
+        format: SerdeFormat,
+    ) -> io::Result<ProvingKey<Self::Affine>>;
+}
+
+/// KZG on BN254. Needs a trusted setup, but can be verified on chain and aggregated.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Kzg;
+
+impl Backend for Kzg {
+    type Scalar = Fr;
+    type Affine = G1Affine;
+    type Params = ParamsKZG<Bn256>;
+    const NAME: &'static str = "kzg-bn254";
+
+    fn setup(k: u32) -> Self::Params {
+        gen_srs(k)
+    }
+
+    fn keygen<C: Circuit<Fr>>(params: &Self::Params, circuit: &C) -> Result<ProvingKey<G1Affine>, Error> {
+        let vk = keygen_vk(params, circuit)?;
+        keygen_pk(params, vk, circuit)
+    }
+
+    fn prove<C: Circuit<Fr>>(
+        params: &Self::Params,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &[Vec<Fr>],
+        config: &ProvingConfig,
+    ) -> Result<Vec<u8>, Error> {
+        type Scheme = KZGCommitmentScheme<Bn256>;
+        match (config.multi_open, config.transcript) {
+            (MultiOpen::Gwc, TranscriptKind::Blake2b) => {
+                write_proof::<Scheme, ProverGWC<_>, _, Blake2bWriter<_>, _>(params, pk, circuit, instances)
+            }
+            (MultiOpen::Gwc, TranscriptKind::Evm) => {
+                write_proof::<Scheme, ProverGWC<_>, _, EvmWriter, _>(params, pk, circuit, instances)
+            }
+            (MultiOpen::Gwc, TranscriptKind::Poseidon) => {
+                write_proof::<Scheme, ProverGWC<_>, _, PoseidonWriter, _>(params, pk, circuit, instances)
+            }
+            (MultiOpen::Shplonk, TranscriptKind::Blake2b) => {
+                write_proof::<Scheme, ProverSHPLONK<_>, _, Blake2bWriter<_>, _>(params, pk, circuit, instances)
+            }
+            (MultiOpen::Shplonk, TranscriptKind::Evm) => {
+                write_proof::<Scheme, ProverSHPLONK<_>, _, EvmWriter, _>(params, pk, circuit, instances)
+            }
+            (MultiOpen::Shplonk, TranscriptKind::Poseidon) => {
+                write_proof::<Scheme, ProverSHPLONK<_>, _, PoseidonWriter, _>(params, pk, circuit, instances)
+            }
+        }
+    }
+
+    fn verify(
+        params: &Self::Params,
+        vk: &VerifyingKey<G1Affine>,
+        instances: &[Vec<Fr>],
+        proof: &[u8],
+        config: &ProvingConfig,
+    ) -> bool {
+        type Scheme = KZGCommitmentScheme<Bn256>;
+        let strategy = KzgStrategy::new(params.verifier_params());
+        match (config.multi_open, config.transcript) {
+            (MultiOpen::Gwc, TranscriptKind::Blake2b) => {
+                read_proof::<Scheme, VerifierGWC<_>, _, Blake2bReader<_>, _>(params, vk, strategy, instances, proof)
+            }
+            (MultiOpen::Gwc, TranscriptKind::Evm) => {
+                read_proof::<Scheme, VerifierGWC<_>, _, EvmReader, _>(params, vk, strategy, instances, proof)
+            }
+            (MultiOpen::Gwc, TranscriptKind::Poseidon) => {
+                read_proof::<Scheme, VerifierGWC<_>, _, PoseidonReader, _>(params, vk, strategy, instances, proof)
+            }
+            (MultiOpen::Shplonk, TranscriptKind::Blake2b) => {
+                read_proof::<Scheme, VerifierSHPLONK<_>, _, Blake2bReader<_>, _>(params, vk, strategy, instances, proof)
+            }
+            (MultiOpen::Shplonk, TranscriptKind::Evm) => {
+                read_proof::<Scheme, VerifierSHPLONK<_>, _, EvmReader, _>(params, vk, strategy, instances, proof)
+            }
+            (MultiOpen::Shplonk, TranscriptKind::Poseidon) => {
+                read_proof::<Scheme, VerifierSHPLONK<_>, _, PoseidonReader, _>(params, vk, strategy, instances, proof)
+            }
+        }
+        .is_ok()
+    }
+
+    fn write_vk<W: Write>(vk: &VerifyingKey<G1Affine>, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        vk.write(writer, format)
+    }
+
+    fn read_vk<R: Read, C: Circuit<Self::Scalar>>(reader: &mut R, format: SerdeFormat) -> io::Result<VerifyingKey<G1Affine>> {
+        VerifyingKey::read::<_, C>(reader, format)
+    }
+
+    fn write_pk<W: Write>(pk: &ProvingKey<G1Affine>, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        pk.write(writer, format)
+    }
+
+    fn read_pk<R: Read, C: Circuit<Self::Scalar>>(reader: &mut R, format: SerdeFormat) -> io::Result<ProvingKey<G1Affine>> {
+        ProvingKey::read::<_, C>(reader, format)
+    }
+}
+
+/// IPA on the Pasta cycle, committing on Vesta so circuits are over its scalar field
+/// (the Pallas base field). There is no trusted setup, but no EVM verifier either, and
+/// only Blake2b transcripts are supported. The multi-open setting does not apply.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ipa;
+
+impl Backend for Ipa {
+    type Scalar = Fp;
+    type Affine = EqAffine;
+    type Params = ParamsIPA<EqAffine>;
+    const NAME: &'static str = "ipa-pasta";
+
+    fn setup(k: u32) -> Self::Params {
+        ParamsIPA::<EqAffine>::new(k)
+    }
+
+    fn keygen<C: Circuit<Fp>>(params: &Self::Params, circuit: &C) -> Result<ProvingKey<EqAffine>, Error> {
+        let vk = keygen_vk(params, circuit)?;
+        keygen_pk(params, vk, circuit)
+    }
+
+    fn prove<C: Circuit<Fp>>(
+        params: &Self::Params,
+        pk: &ProvingKey<EqAffine>,
+        circuit: C,
+        instances: &[Vec<Fp>],
+        config: &ProvingConfig,
+    ) -> Result<Vec<u8>, Error> {
+        if config.transcript != TranscriptKind::Blake2b {
+            return Err(unsupported(config));
+        }
+
+        write_proof::<IPACommitmentScheme<EqAffine>, ProverIPA<_>, _, Blake2bWriter<_>, _>(
+            params, pk, circuit, instances,
+        )
+    }
+
+    fn verify(
+        params: &Self::Params,
+        vk: &VerifyingKey<EqAffine>,
+        instances: &[Vec<Fp>],
+        proof: &[u8],
+        config: &ProvingConfig,
+    ) -> bool {
+        config.transcript == TranscriptKind::Blake2b
+            && read_proof::<IPACommitmentScheme<EqAffine>, VerifierIPA<_>, _, Blake2bReader<_>, _>(
+                params,
+                vk,
+                IpaStrategy::new(params),
+                instances,
+                proof,
+            )
+            .is_ok()
+    }
+
+    fn write_vk<W: Write>(vk: &VerifyingKey<EqAffine>, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        vk.write(writer, format)
+    }
+
+    fn read_vk<R: Read, C: Circuit<Self::Scalar>>(reader: &mut R, format: SerdeFormat) -> io::Result<VerifyingKey<EqAffine>> {
+        VerifyingKey::read::<_, C>(reader, format)
+    }
+
+    fn write_pk<W: Write>(pk: &ProvingKey<EqAffine>, writer: &mut W, format: SerdeFormat) -> io::Result<()> {
+        pk.write(writer, format)
+    }
+
+    fn read_pk<R: Read, C: Circuit<Self::Scalar>>(reader: &mut R, format: SerdeFormat) -> io::Result<ProvingKey<EqAffine>> {
+        ProvingKey::read::<_, C>(reader, format)
+    }
+}
+
+fn write_proof<'params, Scheme, P, E, T, C>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuit: C,
+    instances: &[Vec<Scheme::Scalar>],
+) -> Result<Vec<u8>, Error>
+where
+    Scheme: CommitmentScheme,
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    P: Prover<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    T: TranscriptWriterBuffer<Vec<u8>, Scheme::Curve, E>,
+    C: Circuit<Scheme::Scalar>,
+{
+    let instances = instances.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    let mut transcript = T::init(Vec::new());
+    create_proof::<Scheme, P, _, _, _, _>(
+        params,
+        pk,
+        &[circuit],
+        &[&instances],
+        OsRng,
+        &mut transcript,
+    )?;
+    Ok(transcript.finalize())
+}
+
+fn read_proof<'params, 'proof, Scheme, V, E, T, Strategy>(
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<Scheme::Curve>,
+    strategy: Strategy,
+    instances: &[Vec<Scheme::Scalar>],
+    proof: &'proof [u8],
+) -> Result<Strategy::Output, Error>
+where
+    Scheme: CommitmentScheme,
+    Scheme::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+    V: Verifier<'params, Scheme>,
+    E: EncodedChallenge<Scheme::Curve>,
+    T: TranscriptReadBuffer<&'proof [u8], Scheme::Curve, E>,
+    Strategy: VerificationStrategy<'params, Scheme, V>,
+{
+    let instances = instances.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+    let mut transcript = T::init(proof);
+    verify_proof::<Scheme, V, _, _, _>(params, vk, strategy, &[&instances], &mut transcript)
+}
+
+/// Proves a [`JwtCircuit`] with `B`, using its own public inputs as the instances.
+pub fn prove_jwt<B: Backend>(
+    params: &B::Params,
+    pk: &ProvingKey<B::Affine>,
+    circuit: JwtCircuit,
+    config: &ProvingConfig,
+) -> Result<Vec<u8>, Error> {
+    let instances = vec![circuit.public_inputs()];
+    B::prove(params, pk, circuit, &instances, config)
+}
+
+/// Verifies a bundled proof of a circuit with the given params. Only the public
+/// parameters and the verifying key are needed, never the witness.
+pub fn verify_jwt<B: Backend>(
+    params: &B::Params,
+    vk: &VerifyingKey<B::Affine>,
+    circuit_params: &JwtCircuitParams,
+    config: &ProvingConfig,
+    proof: &JwtProof,
+) -> bool {
+    proof.circuit_params_hash == circuit_params_hash::<B>(circuit_params, config)
+        && proof.vk_hash == vk_hash(vk)
+        && B::verify(params, vk, &proof.instances(), &proof.proof, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_evm_verifier, gen_pk, gen_snark, gen_srs, prove_jwt, Backend, Ipa, Kzg, ProvingConfig};
+    use crate::circuit::JwtCircuit;
+    use crate::precompute::PreComputed;
+    use halo2_proofs::plonk::Error;
+
+    fn round_trip<B: Backend>(config: ProvingConfig) {
+        let jwt = "{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\"}";
+        let credential = "twitter|337834122";
+
+        let circuit = JwtCircuit::new(PreComputed::new(jwt, credential));
+        let mut instances = vec![circuit.public_inputs::<B::Scalar>()];
+
+        let params = B::setup(17);
+        let pk = B::keygen(&params, &circuit).unwrap();
+        let proof = prove_jwt::<B>(&params, &pk, circuit, &config).unwrap();
+        assert!(B::verify(&params, pk.get_vk(), &instances, &proof, &config));
+
+        // A proof only verifies with the config it was produced with
+        let other = if config == ProvingConfig::NATIVE { ProvingConfig::RECURSIVE } else { ProvingConfig::NATIVE };
+        assert!(!B::verify(&params, pk.get_vk(), &instances, &proof, &other));
+
+        // A proof does not verify against another credential
+        instances[0][15] = <B::Scalar>::from(3);
+        assert!(!B::verify(&params, pk.get_vk(), &instances, &proof, &config));
+    }
+
+    #[test]
+    fn kzg_round_trip() {
+        round_trip::<Kzg>(ProvingConfig::NATIVE);
+    }
+
+    #[test]
+    fn kzg_evm_round_trip() {
+        round_trip::<Kzg>(ProvingConfig::EVM);
+    }
+
+    #[test]
+    fn kzg_recursive_round_trip() {
+        round_trip::<Kzg>(ProvingConfig::RECURSIVE);
+    }
+
+    #[test]
+    fn ipa_round_trip() {
+        round_trip::<Ipa>(ProvingConfig::NATIVE);
+    }
+
+    #[test]
+    fn other_transcripts_are_unsupported() {
+        let jwt = "{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\"}";
+        let circuit = JwtCircuit::new(PreComputed::new(jwt, "twitter|337834122"));
+        let params = gen_srs(circuit.min_params().unwrap().k);
+        let pk = gen_pk(&params, &circuit);
+
+        for config in [ProvingConfig::NATIVE, ProvingConfig::EVM] {
+            assert!(matches!(gen_snark(&params, &pk, circuit.clone(), None, &config), Err(Error::Transcript(_))));
+        }
+        for config in [ProvingConfig::NATIVE, ProvingConfig::RECURSIVE] {
+            assert!(matches!(gen_evm_verifier(&params, pk.get_vk(), vec![16], &config), Err(Error::Transcript(_))));
+        }
+    }
 }
@@ -0,0 +1,467 @@
+//! RSASSA-PSS signature verification (`PS256`), following RFC 8017 section 8.1.2 with
+//! SHA-256, MGF1-SHA-256 and a salt as long as the hash, on 2048-bit moduli.
+
+use std::fmt;
+
+use ff::PrimeField;
+use halo2_maingate::{AssignedValue, MainGateInstructions, RegionCtx, Term};
+use halo2_proofs::{
+    circuit::{Layouter, Value},
+    plonk::Error,
+};
+use num_bigint::BigUint;
+
+use crate::bytes::BytesChip;
+use crate::rsa::{limbs_as_field, AssignedBigUint, RsaChip, MODULUS_BITS, PUBLIC_EXPONENT};
+use crate::secret::{debug_witness, SecretBytes};
+use crate::sha256::{Sha256, Table16Chip, DIGEST_SIZE};
+use crate::util::{pad_sha256_bytes, sha256_hash_bytes_digests};
+
+/// Length of the encoded message `EM`, in bytes.
+pub const EM_LEN: usize = MODULUS_BITS / 8;
+/// Length of a SHA-256 digest, in bytes.
+pub const HASH_LEN: usize = 32;
+/// Length of the salt, in bytes.
+pub const SALT_LEN: usize = HASH_LEN;
+/// Length of the data block `DB = PS || 0x01 || salt`, in bytes.
+pub const DB_LEN: usize = EM_LEN - HASH_LEN - 1;
+
+const PS_LEN: usize = DB_LEN - SALT_LEN - 1;
+const MGF1_ROUNDS: usize = (DB_LEN + HASH_LEN - 1) / HASH_LEN;
+const M_PRIME_ZEROS: usize = 8;
+const TRAILER: u8 = 0xbc;
+const SEPARATOR: u8 = 0x01;
+
+/// Why a `PS256` signature is rejected before proving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ps256Error {
+    /// The modulus is not 2048 bits long.
+    UnsupportedModulus,
+    /// The signature representative is not less than the modulus.
+    SignatureOutOfRange,
+    /// The encoded message recovered from the signature is not a PSS encoding.
+    InvalidEncoding(&'static str),
+    /// The signature is not over the message.
+    Mismatch,
+}
+
+impl fmt::Display for Ps256Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ps256Error::UnsupportedModulus => write!(f, "only 2048-bit moduli are supported"),
+            Ps256Error::SignatureOutOfRange => write!(f, "signature representative out of range"),
+            Ps256Error::InvalidEncoding(reason) => write!(f, "invalid PSS encoding: {}", reason),
+            Ps256Error::Mismatch => write!(f, "PSS signature does not match the message"),
+        }
+    }
+}
+
+impl std::error::Error for Ps256Error {}
+
+/// Off-circuit witness of a `PS256` signature over a message. Its `Debug` output only
+/// shows the public modulus.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct Ps256PreComputed {
+    modulus: BigUint,
+    /// Big-endian signature.
+    signature: SecretBytes,
+}
+
+impl Ps256PreComputed {
+    /// Recovers the encoded message of `signature` under the big-endian `modulus` and
+    /// checks it against `message`, so a prover does not run on a signature the circuit
+    /// rejects.
+    pub fn new(message: &[u8], modulus: &[u8], signature: &[u8]) -> Result<Self, Ps256Error> {
+        let modulus = BigUint::from_bytes_be(modulus);
+        let signature = BigUint::from_bytes_be(signature);
+        if modulus.bits() as usize != MODULUS_BITS {
+            return Err(Ps256Error::UnsupportedModulus);
+        }
+        if signature >= modulus {
+            return Err(Ps256Error::SignatureOutOfRange);
+        }
+
+        let em = signature.modpow(&BigUint::from(PUBLIC_EXPONENT), &modulus);
+        let em_bytes = SecretBytes::from(em.to_bytes_be());
+        let mut encoded_message = vec![0u8; EM_LEN - em_bytes.len()];
+        encoded_message.extend_from_slice(&em_bytes);
+        let encoded_message = SecretBytes::from(encoded_message);
+
+        let (masked_db, rest) = encoded_message.split_at(DB_LEN);
+        let (h, trailer) = rest.split_at(HASH_LEN);
+        if trailer[0] != TRAILER {
+            return Err(Ps256Error::InvalidEncoding("trailer"));
+        }
+        if masked_db[0] & 0x80 != 0 {
+            return Err(Ps256Error::InvalidEncoding("leftmost bit"));
+        }
+
+        let db = SecretBytes::from(
+            mgf1(h)
+                .iter()
+                .zip(masked_db.iter())
+                .map(|(mask, masked)| mask ^ masked)
+                .collect::<Vec<_>>(),
+        );
+        if db[1..PS_LEN].iter().any(|b| *b != 0) || db[0] & 0x7f != 0 {
+            return Err(Ps256Error::InvalidEncoding("padding"));
+        }
+        if db[PS_LEN] != SEPARATOR {
+            return Err(Ps256Error::InvalidEncoding("separator"));
+        }
+
+        let salt = &db[PS_LEN + 1..];
+        let message_hash = SecretBytes::from(&sha256_hash_bytes_digests(message)[..]);
+        if sha256_hash_bytes_digests(&m_prime(&message_hash, salt))[..] != *h {
+            return Err(Ps256Error::Mismatch);
+        }
+
+        Ok(Self {
+            modulus,
+            signature: signature.to_bytes_be().into(),
+        })
+    }
+
+    /// A witness that is not a signature, with a modulus of the right size and the
+    /// signature 1, unchecked. It lays out like any other, so it only serves to measure
+    /// the circuit.
+    pub(crate) fn placeholder() -> Self {
+        Self {
+            modulus: (BigUint::from(1u8) << (MODULUS_BITS - 1)) + 1u8,
+            signature: vec![1u8].into(),
+        }
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    /// The signature as an integer, rebuilt on every call into a copy that is not wiped.
+    pub fn signature(&self) -> BigUint {
+        BigUint::from_bytes_be(&self.signature)
+    }
+
+    /// The modulus limbs, exposed as public inputs of the verification.
+    pub fn public_inputs<F: PrimeField>(&self) -> Vec<F> {
+        limbs_as_field(&self.modulus)
+    }
+
+    pub fn log_all(&self) {
+        log::info!("[Pre-Constrained] PS256 Modulus Bits: {:?}", self.modulus.bits());
+        debug_witness!("[Pre-Constrained] PS256 Signature: {:?}", self.signature);
+    }
+}
+
+impl fmt::Debug for Ps256PreComputed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Ps256PreComputed");
+        debug.field("modulus", &self.modulus);
+
+        #[cfg(feature = "unsafe-debug")]
+        debug.field("signature", &self.signature);
+
+        debug.finish_non_exhaustive()
+    }
+}
+
+fn m_prime(message_hash: &[u8], salt: &[u8]) -> SecretBytes {
+    let mut result = Vec::with_capacity(M_PRIME_ZEROS + message_hash.len() + salt.len());
+    result.resize(M_PRIME_ZEROS, 0);
+    result.extend_from_slice(message_hash);
+    result.extend_from_slice(salt);
+    result.into()
+}
+
+fn mgf1(seed: &[u8]) -> SecretBytes {
+    let mut result = Vec::with_capacity(MGF1_ROUNDS * HASH_LEN);
+    for counter in 0..MGF1_ROUNDS as u32 {
+        let mut preimage = Vec::with_capacity(seed.len() + 4);
+        preimage.extend_from_slice(seed);
+        preimage.extend_from_slice(&counter.to_be_bytes());
+        let preimage = SecretBytes::from(preimage);
+        result.extend_from_slice(&sha256_hash_bytes_digests(&preimage));
+    }
+    result.truncate(DB_LEN);
+    result.into()
+}
+
+/// `suffix` followed by the SHA-256 padding of a message of `prefix_len` bytes and
+/// then `suffix`.
+fn padded_suffix(prefix_len: usize, suffix: &[u8]) -> Vec<u8> {
+    let mut message = vec![0u8; prefix_len];
+    message.extend_from_slice(suffix);
+    pad_sha256_bytes(&message).split_off(prefix_len)
+}
+
+/// A gadget that verifies a `PS256` signature with an [`RsaChip`] and a [`Table16Chip`].
+#[derive(Clone, Debug)]
+pub struct Ps256Chip<F: PrimeField> {
+    rsa_chip: RsaChip<F>,
+    bytes_chip: BytesChip<F>,
+}
+
+impl<F: PrimeField> Ps256Chip<F> {
+    pub fn new(rsa_chip: RsaChip<F>) -> Self {
+        let bytes_chip = BytesChip::new(rsa_chip.main_gate().clone(), rsa_chip.range_chip().clone());
+        Self { rsa_chip, bytes_chip }
+    }
+
+    /// Constrains the signature to be over the message whose SHA-256 digest words are
+    /// `message_digest`, under the public modulus, and returns the assigned modulus to
+    /// be exposed as public inputs. Every hash is computed from assigned bytes: the
+    /// MGF1 seed is `H` of the encoded message, and `M'` is built from `message_digest`
+    /// and the salt recovered from the data block.
+    pub fn verify(
+        &self,
+        sha256_chip: Table16Chip<F>,
+        mut layouter: impl Layouter<F>,
+        precomputed: &Ps256PreComputed,
+        message_digest: &[AssignedValue<F>; DIGEST_SIZE],
+    ) -> Result<AssignedBigUint<F>, Error> {
+        let gate = self.rsa_chip.main_gate();
+
+        // 1. EM = s^e mod n = maskedDB || H || 0xbc
+        let (modulus, em) = layouter.assign_region(|| "encoded message", |region| {
+            let ctx = &mut RegionCtx::new(region, 0);
+            let modulus = self.rsa_chip.assign(ctx, Value::known(precomputed.modulus.clone()))?;
+            let signature = self
+                .rsa_chip
+                .assign(ctx, Value::known(precomputed.signature()))?;
+            self.rsa_chip.assert_less_than(ctx, &signature, &modulus)?;
+            let em = self.rsa_chip.pow_public_exponent(ctx, &signature, &modulus)?;
+            let em = self.rsa_chip.to_be_bytes(ctx, &em)?;
+
+            let trailer = gate.assign_constant(ctx, F::from(TRAILER as u64))?;
+            gate.assert_equal(ctx, &em[EM_LEN - 1], &trailer)?;
+            Ok((modulus, em))
+        })?;
+        let (masked_db, rest) = em.split_at(DB_LEN);
+        let h = &rest[..HASH_LEN];
+
+        // 2. dbMask = MGF1(H, emLen - hLen - 1), hashing H || I2OSP(counter, 4). The
+        // counter and the padding are fixed
+        let mut mgf1 = Vec::with_capacity(MGF1_ROUNDS);
+        for counter in 0..MGF1_ROUNDS as u32 {
+            let mut preimage = h.to_vec();
+            preimage.extend(self.assign_constants(
+                layouter.namespace(|| format!("mgf1_{}_counter", counter)),
+                &padded_suffix(HASH_LEN, &counter.to_be_bytes()),
+            )?);
+            mgf1.push(Sha256::digest_bytes_assigned(
+                sha256_chip.clone(),
+                layouter.namespace(|| format!("sha256_mgf1_{}", counter)),
+                &preimage,
+            )?);
+        }
+
+        // 3. DB = maskedDB xor dbMask = PS || 0x01 || salt, with the leftmost bit cleared
+        let (salt, message_hash) = layouter.assign_region(|| "data block", |region| {
+            let ctx = &mut RegionCtx::new(region, 0);
+            let mut db_mask = Vec::with_capacity(MGF1_ROUNDS * HASH_LEN);
+            for digest in mgf1.iter() {
+                db_mask.extend(self.digest_to_bytes(ctx, digest)?);
+            }
+            db_mask.truncate(DB_LEN);
+
+            // The leftmost bit of maskedDB is zero and ignored in DB
+            let masked_bits = gate.to_bits(ctx, &masked_db[0], 8)?;
+            let mask_bits = gate.to_bits(ctx, &db_mask[0], 8)?;
+            gate.assert_zero(ctx, &masked_bits[7])?;
+            for bit_index in 0..7 {
+                gate.assert_equal(ctx, &masked_bits[bit_index], &mask_bits[bit_index])?;
+            }
+
+            // A zero byte of DB means maskedDB and dbMask agree on it
+            for index in 1..PS_LEN {
+                gate.assert_equal(ctx, &masked_db[index], &db_mask[index])?;
+            }
+
+            let separator = self.xor_bytes(ctx, &masked_db[PS_LEN], &db_mask[PS_LEN])?;
+            let separator_value = gate.assign_constant(ctx, F::from(SEPARATOR as u64))?;
+            gate.assert_equal(ctx, &separator, &separator_value)?;
+
+            let salt = (PS_LEN + 1..DB_LEN)
+                .map(|index| self.xor_bytes(ctx, &masked_db[index], &db_mask[index]))
+                .collect::<Result<Vec<_>, _>>()?;
+            let message_hash = self.digest_to_bytes(ctx, message_digest)?;
+            Ok((salt, message_hash))
+        })?;
+
+        // 4. M' = 0x00 * 8 || mHash || salt, followed by its fixed padding
+        let mut m_prime = self.assign_constants(
+            layouter.namespace(|| "m_prime_zeros"),
+            &[0u8; M_PRIME_ZEROS],
+        )?;
+        m_prime.extend(message_hash);
+        m_prime.extend(salt);
+        m_prime.extend(self.assign_constants(
+            layouter.namespace(|| "m_prime_padding"),
+            &padded_suffix(M_PRIME_ZEROS + HASH_LEN + SALT_LEN, &[]),
+        )?);
+        let h_prime = Sha256::digest_bytes_assigned(
+            sha256_chip,
+            layouter.namespace(|| "sha256_m_prime"),
+            &m_prime,
+        )?;
+
+        // 5. H = Hash(M')
+        layouter.assign_region(|| "hash", |region| {
+            let ctx = &mut RegionCtx::new(region, 0);
+            let h_prime = self.digest_to_bytes(ctx, &h_prime)?;
+            self.bytes_chip.assert_bytes_equal(ctx, h, &h_prime)
+        })?;
+
+        Ok(modulus)
+    }
+
+    /// Assigns fixed bytes.
+    fn assign_constants(
+        &self,
+        mut layouter: impl Layouter<F>,
+        bytes: &[u8],
+    ) -> Result<Vec<AssignedValue<F>>, Error> {
+        let gate = self.rsa_chip.main_gate();
+        layouter.assign_region(|| "constants", |region| {
+            let ctx = &mut RegionCtx::new(region, 0);
+            bytes
+                .iter()
+                .map(|byte| gate.assign_constant(ctx, F::from(*byte as u64)))
+                .collect()
+        })
+    }
+
+    /// Splits every assigned word of a digest into range checked bytes, most significant
+    /// first.
+    fn digest_to_bytes(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        digest: &[AssignedValue<F>; DIGEST_SIZE],
+    ) -> Result<Vec<AssignedValue<F>>, Error> {
+        let mut bytes = Vec::with_capacity(HASH_LEN);
+        for word in digest.iter() {
+            bytes.extend(self.bytes_chip.decompose_word(ctx, word)?);
+        }
+
+        Ok(bytes)
+    }
+
+    /// Returns `a xor b` for two range checked bytes.
+    fn xor_bytes(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        a: &AssignedValue<F>,
+        b: &AssignedValue<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        let gate = self.rsa_chip.main_gate();
+        let a_bits = gate.to_bits(ctx, a, 8)?;
+        let b_bits = gate.to_bits(ctx, b, 8)?;
+
+        let mut xor_bits = Vec::with_capacity(8);
+        for (a, b) in a_bits.iter().zip(b_bits.iter()) {
+            // a xor b = a + b - 2ab
+            let ab = gate.mul(ctx, a, b)?;
+            xor_bits.push(gate.compose(
+                ctx,
+                &[
+                    Term::Assigned(a, F::ONE),
+                    Term::Assigned(b, F::ONE),
+                    Term::Assigned(&ab, -F::from(2)),
+                ],
+                F::ZERO,
+            )?);
+        }
+
+        let terms = xor_bits
+            .iter()
+            .enumerate()
+            .map(|(i, bit)| Term::Assigned(bit, F::from(1 << i)))
+            .collect::<Vec<_>>();
+        gate.compose(ctx, &terms, F::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ps256Error, Ps256PreComputed};
+    use crate::circuit::{JwtAlgorithm, JwtCircuit, JwtCircuitParams};
+    use halo2curves::bn256::Fr;
+    use crate::precompute::PreComputed;
+    use crate::secret::SecretBytes;
+
+    use halo2_proofs::dev::MockProver;
+    use num_bigint::BigUint;
+    use ::rsa::{
+        pss::SigningKey,
+        signature::{RandomizedSigner, SignatureEncoding},
+        traits::PublicKeyParts,
+        RsaPrivateKey,
+    };
+    use sha2::Sha256;
+
+    const JWT: &str = "{\"iss\":\"https://bank.example.com/\",\"sub\":\"alice|42\",\"aud\":\"123\"}";
+    const CREDENTIAL: &str = "alice|42";
+
+    /// A fresh modulus and the `PS256` signature of `message` under it.
+    fn sign(message: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut rng = rand::thread_rng();
+        let private_key = RsaPrivateKey::new(&mut rng, 2048).unwrap();
+        let modulus = private_key.n().to_bytes_be();
+        let signature = SigningKey::<Sha256>::new(private_key)
+            .sign_with_rng(&mut rng, message)
+            .to_bytes();
+        (modulus, signature.to_vec())
+    }
+
+    fn satisfied(jwt: &str, ps256: Ps256PreComputed) -> bool {
+        let circuit = JwtCircuit::new_with_algorithm(
+            PreComputed::new(jwt, CREDENTIAL),
+            JwtAlgorithm::Ps256(ps256),
+        );
+        MockProver::run(18, &circuit, vec![circuit.public_inputs::<Fr>()])
+            .unwrap()
+            .verify()
+            .is_ok()
+    }
+
+    #[test]
+    fn e2e_ps256_test() {
+        let (modulus, signature) = sign(JWT.as_bytes());
+        let ps256 = Ps256PreComputed::new(JWT.as_bytes(), &modulus, &signature).unwrap();
+        assert!(satisfied(JWT, ps256));
+    }
+
+    #[test]
+    fn signature_of_another_message_is_rejected() {
+        let (modulus, signature) = sign(JWT.as_bytes());
+        let other = JWT.replace("123", "456");
+        assert_eq!(
+            Ps256PreComputed::new(other.as_bytes(), &modulus, &signature),
+            Err(Ps256Error::Mismatch)
+        );
+        assert_eq!(
+            Ps256PreComputed::new(JWT.as_bytes(), &modulus[1..], &signature),
+            Err(Ps256Error::UnsupportedModulus)
+        );
+
+        // A valid signature, proven for another token
+        let ps256 = Ps256PreComputed {
+            modulus: BigUint::from_bytes_be(&modulus),
+            signature: SecretBytes::from(signature),
+        };
+        assert!(!satisfied(&other, ps256));
+    }
+
+    #[test]
+    fn placeholder_lays_out_like_a_signature() {
+        let (modulus, signature) = sign(JWT.as_bytes());
+        let ps256 = Ps256PreComputed::new(JWT.as_bytes(), &modulus, &signature).unwrap();
+        let circuit = JwtCircuit::new_with_algorithm(
+            PreComputed::new(JWT, CREDENTIAL),
+            JwtAlgorithm::Ps256(ps256),
+        );
+        let params = circuit.min_params().unwrap();
+
+        assert_eq!(params.dry_run().unwrap(), circuit.dimensions().unwrap());
+        assert_eq!(JwtCircuitParams { k: 0, ..params }.with_min_k(), Some(params));
+    }
+}
@@ -0,0 +1,447 @@
+//! Modular arithmetic over 2048-bit RSA moduli, built on the `MainGate` and `RangeChip`
+//! from halo2wrong.
+//!
+//! Integers are held as little-endian limbs of [`LIMB_BITS`] bits, each range checked
+//! through the byte lookup table of the `RangeChip`. A product `a * b = q * n + r` is
+//! checked column by column: the limb products of every column are summed and the
+//! (signed) carry into the next column is witnessed and range checked, so that the
+//! whole identity holds over the integers without wrapping around the field.
+
+use ff::PrimeField;
+use halo2_maingate::{
+    AssignedValue, MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig,
+    RangeInstructions, RegionCtx, Term,
+};
+use halo2_proofs::{
+    circuit::{Layouter, Value},
+    plonk::{ConstraintSystem, Error},
+};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, ToPrimitive, Zero};
+
+/// Bit length of a single limb.
+pub const LIMB_BITS: usize = 64;
+/// Number of limbs of a 2048-bit integer.
+pub const NUMBER_OF_LIMBS: usize = 32;
+/// Bit length of the moduli supported by [`RsaChip`].
+pub const MODULUS_BITS: usize = LIMB_BITS * NUMBER_OF_LIMBS;
+/// The public exponent `e = 2^16 + 1` used by every supported key.
+pub const PUBLIC_EXPONENT: u64 = 65537;
+
+/// Limb size of the lookup table used for range checks.
+pub const RANGE_LIMB_BITS: usize = 8;
+
+// A column of a 32x32 limb product sums at most 64 terms below 2^128, hence the signed
+// carry out of it stays below 2^71 in absolute value.
+const MUL_CARRY_BITS: usize = 72;
+// Carries of an addition of two 32-limb integers are within [-1, 1].
+const ADD_CARRY_BITS: usize = 8;
+
+/// Returns the `index`-th little-endian limb of `value`.
+pub fn limb(value: &BigUint, index: usize) -> u64 {
+    value.to_u64_digits().get(index).copied().unwrap_or(0)
+}
+
+/// Splits `value` into [`NUMBER_OF_LIMBS`] little-endian limbs as field elements.
+pub fn limbs_as_field<F: PrimeField>(value: &BigUint) -> Vec<F> {
+    (0..NUMBER_OF_LIMBS).map(|i| F::from(limb(value, i))).collect()
+}
+
+/// Configuration for a [`RsaChip`].
+#[derive(Clone, Debug)]
+pub struct RsaConfig {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+}
+
+impl RsaConfig {
+    pub fn new(main_gate_config: MainGateConfig, range_config: RangeConfig) -> Self {
+        Self {
+            main_gate_config,
+            range_config,
+        }
+    }
+
+    /// Configures the range chip sharing the columns of `main_gate_config`.
+    pub fn configure<F: PrimeField>(
+        meta: &mut ConstraintSystem<F>,
+        main_gate_config: MainGateConfig,
+    ) -> Self {
+        let range_config =
+            RangeChip::<F>::configure(meta, &main_gate_config, vec![RANGE_LIMB_BITS], vec![]);
+        Self::new(main_gate_config, range_config)
+    }
+}
+
+/// A 2048-bit integer assigned as range checked limbs.
+#[derive(Clone, Debug)]
+pub struct AssignedBigUint<F: PrimeField> {
+    limbs: Vec<AssignedValue<F>>,
+    value: Value<BigUint>,
+}
+
+impl<F: PrimeField> AssignedBigUint<F> {
+    /// Little-endian limbs of the integer.
+    pub fn limbs(&self) -> &[AssignedValue<F>] {
+        &self.limbs
+    }
+
+    pub fn value(&self) -> Value<&BigUint> {
+        self.value.as_ref()
+    }
+}
+
+/// A chip for modular multiplication and exponentiation by [`PUBLIC_EXPONENT`].
+#[derive(Clone, Debug)]
+pub struct RsaChip<F: PrimeField> {
+    main_gate: MainGate<F>,
+    range_chip: RangeChip<F>,
+}
+
+impl<F: PrimeField> RsaChip<F> {
+    pub fn new(config: RsaConfig) -> Self {
+        Self {
+            main_gate: MainGate::new(config.main_gate_config),
+            range_chip: RangeChip::new(config.range_config),
+        }
+    }
+
+    pub fn main_gate(&self) -> &MainGate<F> {
+        &self.main_gate
+    }
+
+    pub fn range_chip(&self) -> &RangeChip<F> {
+        &self.range_chip
+    }
+
+    /// Loads the lookup table required by the range checks.
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.range_chip.load_table(layouter)
+    }
+
+    /// Assigns `value` as [`NUMBER_OF_LIMBS`] range checked limbs.
+    pub fn assign(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        value: Value<BigUint>,
+    ) -> Result<AssignedBigUint<F>, Error> {
+        let limbs = (0..NUMBER_OF_LIMBS)
+            .map(|i| {
+                self.range_chip.assign(
+                    ctx,
+                    value.as_ref().map(|v| F::from(limb(v, i))),
+                    RANGE_LIMB_BITS,
+                    LIMB_BITS,
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(AssignedBigUint { limbs, value })
+    }
+
+    /// Returns `a * b mod modulus`. The result is only guaranteed to be congruent to the
+    /// product and below `2^MODULUS_BITS`; use [`Self::assert_less_than`] to pin down the
+    /// canonical representative.
+    pub fn mul_mod(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        a: &AssignedBigUint<F>,
+        b: &AssignedBigUint<F>,
+        modulus: &AssignedBigUint<F>,
+    ) -> Result<AssignedBigUint<F>, Error> {
+        let (quotient, remainder) = a
+            .value
+            .as_ref()
+            .zip(b.value.as_ref())
+            .zip(modulus.value.as_ref())
+            .map(|((a, b), n)| {
+                let product = a * b;
+                (&product / n, &product % n)
+            })
+            .unzip();
+
+        let quotient = self.assign(ctx, quotient)?;
+        let remainder = self.assign(ctx, remainder)?;
+
+        // a * b - q * n - r = 0
+        let gate = &self.main_gate;
+        let mut columns = vec![vec![]; 2 * NUMBER_OF_LIMBS - 1];
+        for i in 0..NUMBER_OF_LIMBS {
+            for j in 0..NUMBER_OF_LIMBS {
+                columns[i + j].push((gate.mul(ctx, &a.limbs[i], &b.limbs[j])?, F::ONE));
+                columns[i + j].push((
+                    gate.mul(ctx, &quotient.limbs[i], &modulus.limbs[j])?,
+                    -F::ONE,
+                ));
+            }
+        }
+        for (i, limb) in remainder.limbs.iter().enumerate() {
+            columns[i].push((limb.clone(), -F::ONE));
+        }
+
+        let carries = a
+            .value
+            .as_ref()
+            .zip(b.value.as_ref())
+            .zip(quotient.value.as_ref())
+            .zip(modulus.value.as_ref())
+            .zip(remainder.value.as_ref())
+            .map(|((((a, b), q), n), r)| {
+                let mut columns = product_columns(a, b);
+                for (column, qn) in columns.iter_mut().zip(product_columns(q, n)) {
+                    *column -= qn;
+                }
+                for (i, column) in columns.iter_mut().take(NUMBER_OF_LIMBS).enumerate() {
+                    *column -= BigInt::from(limb(r, i));
+                }
+                signed_carries(&columns)
+            });
+
+        self.assert_columns_zero(ctx, &columns, F::ZERO, carries, MUL_CARRY_BITS)?;
+
+        Ok(remainder)
+    }
+
+    /// Constrains `a < modulus`.
+    pub fn assert_less_than(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        a: &AssignedBigUint<F>,
+        modulus: &AssignedBigUint<F>,
+    ) -> Result<(), Error> {
+        // Witness d = n - a - 1, whose limbs are range checked, then d + a + 1 - n = 0
+        let difference = a
+            .value
+            .as_ref()
+            .zip(modulus.value.as_ref())
+            .map(|(a, n)| {
+                if a < n {
+                    n - a - BigUint::one()
+                } else {
+                    BigUint::zero()
+                }
+            });
+        let difference = self.assign(ctx, difference)?;
+
+        let columns = (0..NUMBER_OF_LIMBS)
+            .map(|i| {
+                vec![
+                    (difference.limbs[i].clone(), F::ONE),
+                    (a.limbs[i].clone(), F::ONE),
+                    (modulus.limbs[i].clone(), -F::ONE),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let carries = difference
+            .value
+            .as_ref()
+            .zip(a.value.as_ref())
+            .zip(modulus.value.as_ref())
+            .map(|((d, a), n)| {
+                let mut columns = (0..NUMBER_OF_LIMBS)
+                    .map(|i| BigInt::from(limb(d, i)) + limb(a, i) - limb(n, i))
+                    .collect::<Vec<_>>();
+                columns[0] += BigInt::one();
+                signed_carries(&columns)
+            });
+
+        self.assert_columns_zero(ctx, &columns, F::ONE, carries, ADD_CARRY_BITS)
+    }
+
+    /// Returns `base^65537 mod modulus` in its canonical form.
+    pub fn pow_public_exponent(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        base: &AssignedBigUint<F>,
+        modulus: &AssignedBigUint<F>,
+    ) -> Result<AssignedBigUint<F>, Error> {
+        let mut acc = base.clone();
+        for _ in 0..16 {
+            acc = self.mul_mod(ctx, &acc, &acc, modulus)?;
+        }
+        let result = self.mul_mod(ctx, &acc, base, modulus)?;
+        self.assert_less_than(ctx, &result, modulus)?;
+
+        Ok(result)
+    }
+
+    /// Decomposes `a` into `MODULUS_BITS / 8` range checked bytes, most significant first.
+    pub fn to_be_bytes(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        a: &AssignedBigUint<F>,
+    ) -> Result<Vec<AssignedValue<F>>, Error> {
+        let mut bytes = Vec::with_capacity(MODULUS_BITS / 8);
+        for limb in a.limbs.iter().rev() {
+            let (composed, limb_bytes) =
+                self.range_chip
+                    .decompose(ctx, limb.value().copied(), RANGE_LIMB_BITS, LIMB_BITS)?;
+            self.main_gate.assert_equal(ctx, &composed, limb)?;
+            bytes.extend(limb_bytes.into_iter().rev());
+        }
+
+        Ok(bytes)
+    }
+
+    /// Constrains `Σ_k (columns[k] + constant·[k = 0]) · 2^(LIMB_BITS·k) = 0` over the
+    /// integers, given the signed carries out of every column but the last.
+    fn assert_columns_zero(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        columns: &[Vec<(AssignedValue<F>, F)>],
+        constant: F,
+        carries: Value<Vec<BigInt>>,
+        carry_bits: usize,
+    ) -> Result<(), Error> {
+        let gate = &self.main_gate;
+        let base = F::from_u128(1 << LIMB_BITS);
+        // Carries are shifted by 2^(carry_bits - 1) so that they can be range checked
+        let shift = BigInt::one() << (carry_bits - 1);
+        let shift_f = F::from_u128(1 << (carry_bits - 1));
+
+        let carries = carries
+            .map(|carries| {
+                carries
+                    .into_iter()
+                    .map(|carry| (carry + &shift).to_u128().expect("carry out of range"))
+                    .collect::<Vec<_>>()
+            })
+            .transpose_vec(columns.len() - 1);
+
+        let mut carry_in: Option<AssignedValue<F>> = None;
+        for (k, column) in columns.iter().enumerate() {
+            let mut terms = column
+                .iter()
+                .map(|(cell, coeff)| Term::Assigned(cell, *coeff))
+                .collect::<Vec<_>>();
+            let mut offset = if k == 0 { constant } else { F::ZERO };
+
+            if let Some(carry_in) = carry_in.as_ref() {
+                terms.push(Term::Assigned(carry_in, F::ONE));
+                offset -= shift_f;
+            }
+
+            let carry_out = match carries.get(k).copied() {
+                Some(carry) => {
+                    let carry = self.range_chip.assign(
+                        ctx,
+                        carry.map(F::from_u128),
+                        RANGE_LIMB_BITS,
+                        carry_bits,
+                    )?;
+                    offset += shift_f * base;
+                    Some(carry)
+                }
+                None => None,
+            };
+            if let Some(carry_out) = carry_out.as_ref() {
+                terms.push(Term::Assigned(carry_out, -base));
+            }
+
+            let sum = gate.compose(ctx, &terms, offset)?;
+            gate.assert_zero(ctx, &sum)?;
+
+            carry_in = carry_out;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the `2 * NUMBER_OF_LIMBS - 1` column sums of the limb-wise product `a * b`.
+fn product_columns(a: &BigUint, b: &BigUint) -> Vec<BigInt> {
+    let mut columns = vec![BigInt::zero(); 2 * NUMBER_OF_LIMBS - 1];
+    for i in 0..NUMBER_OF_LIMBS {
+        for j in 0..NUMBER_OF_LIMBS {
+            columns[i + j] += BigInt::from(limb(a, i)) * limb(b, j);
+        }
+    }
+    columns
+}
+
+/// Returns the carries out of every column but the last, for columns that sum to zero.
+fn signed_carries(columns: &[BigInt]) -> Vec<BigInt> {
+    let mut carry = BigInt::zero();
+    columns[..columns.len() - 1]
+        .iter()
+        .map(|column| {
+            // Exact division: the columns below k sum to a multiple of 2^(LIMB_BITS·k)
+            carry = (column + &carry) >> LIMB_BITS;
+            carry.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RsaChip, RsaConfig, MODULUS_BITS, PUBLIC_EXPONENT};
+    use halo2_maingate::{MainGate, RegionCtx};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2curves::bn256::Fr;
+    use num_bigint::{BigUint, RandBigInt};
+
+    #[derive(Clone, Default)]
+    struct PowCircuit {
+        base: BigUint,
+        modulus: BigUint,
+    }
+
+    impl Circuit<Fr> for PowCircuit {
+        type Config = RsaConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let main_gate_config = MainGate::<Fr>::configure(meta);
+            RsaConfig::configure(meta, main_gate_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = RsaChip::<Fr>::new(config);
+            chip.load(&mut layouter)?;
+
+            let expected = self
+                .base
+                .modpow(&BigUint::from(PUBLIC_EXPONENT), &self.modulus);
+            layouter.assign_region(
+                || "pow",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let base = chip.assign(ctx, Value::known(self.base.clone()))?;
+                    let modulus = chip.assign(ctx, Value::known(self.modulus.clone()))?;
+                    let result = chip.pow_public_exponent(ctx, &base, &modulus)?;
+                    result.value().assert_if_known(|v| **v == expected);
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn pow_public_exponent() {
+        let mut rng = rand::thread_rng();
+        let modulus = rng.gen_biguint(MODULUS_BITS as u64) | (BigUint::from(1u8) << (MODULUS_BITS - 1));
+        let base = rng.gen_biguint_below(&modulus);
+
+        let circuit = PowCircuit { base, modulus };
+        let prover = match MockProver::<Fr>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -0,0 +1,104 @@
+//! Handling of the prover's private witness: the token, the credential and what is
+//! derived from them.
+//!
+//! [`SecretBytes`] holds a private buffer, wiped when dropped and redacted from `Debug`
+//! output. It holds the token and the credential, their padded bytes, and the `PS256`
+//! signature with the buffers its check derives: the encoded message, the data block
+//! and the MGF1 and `M'` preimages.
+//!
+//! Only those buffers are wiped. The message words, big integers and field elements
+//! built from them are plain copies, and none of them is wiped:
+//!
+//! - the `BlockWord`s handed to the SHA-256 chip;
+//! - the signature as a `BigUint`, rebuilt by `Ps256PreComputed::signature` on every
+//!   synthesis, and the signature and encoded message integers of
+//!   `Ps256PreComputed::new`;
+//! - the powers of the signature, quotients and carries the `RsaChip` witnesses;
+//! - the cells the circuit assigns, and whatever the proving system derives from them.
+//!
+//! The witness may thus linger in freed memory after a proof.
+//!
+//! Logs only report sizes and timings of the witness, unless the `unsafe-debug`
+//! feature is enabled, which prints buffers and logs witness values at debug level. It
+//! is meant for debugging circuits locally, never for a prover handling real tokens.
+
+use std::fmt;
+use std::ops::Deref;
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Bytes of the private witness, zeroized on drop.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    /// The bytes, to feed the circuit. Copies of them are not wiped.
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecretBytes {}
+
+impl fmt::Debug for SecretBytes {
+    #[cfg(not(feature = "unsafe-debug"))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes(<{} bytes redacted>)", self.0.len())
+    }
+
+    #[cfg(feature = "unsafe-debug")]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SecretBytes").field(&self.0).finish()
+    }
+}
+
+/// Logs witness values at debug level, only with the `unsafe-debug` feature. The
+/// arguments are not evaluated otherwise.
+macro_rules! debug_witness {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "unsafe-debug")]
+        log::debug!($($arg)+);
+    };
+}
+
+pub(crate) use debug_witness;
+
+#[cfg(all(test, not(feature = "unsafe-debug")))]
+mod tests {
+    use super::SecretBytes;
+
+    #[test]
+    fn debug_is_redacted() {
+        let secret = SecretBytes::from(b"twitter|337834122".to_vec());
+        let debug = format!("{:?}", secret);
+
+        assert_eq!(debug, "SecretBytes(<17 bytes redacted>)");
+        assert_eq!(secret.expose(), b"twitter|337834122");
+    }
+}
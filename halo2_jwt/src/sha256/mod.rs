@@ -1,6 +1,79 @@
-//! The [SHA-256] hash function.
+//! The [SHA-256] hash function, as a gadget other circuits can reuse.
+//!
+//! The [`Sha256`] gadget hashes messages with any chip implementing
+//! [`Sha256Instructions`]. The crate ships [`Table16Chip`], whose entry points follow
+//! the usual chip lifecycle:
+//!
+//! - [`Table16Chip::configure`] in [`Circuit::configure`], which allocates the columns,
+//!   gates and the spread lookup table and returns a [`Table16Config`];
+//! - [`Table16Chip::load`] once per circuit in [`Circuit::synthesize`], which fills the
+//!   lookup table (`2^16` rows, so the circuit needs `k >= 17`);
+//!   [`Table16Chip::configure_with_table`] with [`SpreadTableKind::Split`] configures a
+//!   table of 1773 rows instead, for circuits small enough to fit in fewer rows;
+//! - [`Table16Chip::construct`] from the config, then [`Sha256::digest`] on padded
+//!   [`BlockWord`]s, [`Sha256::digest_bytes`] on padded assigned bytes, or
+//!   [`Sha256::new`] / [`Sha256::from_midstate`], [`Sha256::update`] and
+//!   [`Sha256::finalize`] to hash incrementally. [`Sha256::finalize_assigned`] and
+//!   [`Sha256::digest_bytes_assigned`] return the cells of the digest instead, to copy
+//!   or expose it.
+//!
+//! The gadget never pads in-circuit: [`pad_sha256_bytes`] and [`padded_blockwords`]
+//! prepare a message, and [`sha256_midstate`] compresses a public prefix off-circuit.
+//!
+//! ```no_run
+//! use halo2_jwt::sha256::{padded_blockwords, Sha256, Table16Chip, Table16Config};
+//! use halo2_proofs::{
+//!     circuit::{Layouter, SimpleFloorPlanner},
+//!     plonk::{Circuit, ConstraintSystem, Error},
+//! };
+//! use halo2curves::bn256::Fr;
+//!
+//! struct Hash(Vec<u8>);
+//!
+//! impl Circuit<Fr> for Hash {
+//!     type Config = Table16Config<Fr>;
+//!     type FloorPlanner = SimpleFloorPlanner;
+//!
+//!     fn without_witnesses(&self) -> Self {
+//!         Hash(self.0.clone())
+//!     }
+//!
+//!     fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+//!         Table16Chip::configure(meta)
+//!     }
+//!
+//!     fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+//!         let chip = Table16Chip::construct(config.clone());
+//!         Table16Chip::load(config, &mut layouter)?;
+//!         let digest = Sha256::digest(chip, layouter.namespace(|| "sha256"), &padded_blockwords(&self.0))?;
+//!         // digest.0 holds the 8 big-endian words of the hash
+//!         Ok(())
+//!     }
+//! }
+//! ```
+//!
+//! # Soundness
+//!
+//! A [`BlockWord`] is a plain [`Value`](halo2_proofs::circuit::Value), not a cell. The
+//! words fed to [`Sha256::update`] or [`Sha256::digest`] are witnessed by the chip, and
+//! those returned by [`Sha256::finalize`] and [`Sha256::digest`] are not bound to
+//! anything, so no other gadget can rely on them. To prove a statement about a hash,
+//! feed assigned bytes with [`Sha256::update_bytes`] or [`Sha256::digest_bytes`] and
+//! take the digest cells from [`Sha256::finalize_assigned`] or
+//! [`Sha256::digest_bytes_assigned`]. The state a block starts from is the IV, a public
+//! midstate fixed in the circuit, or a copy of the state the previous block ends with.
+//!
+//! # Stability
+//!
+//! Everything reachable from this module follows the crate's semver: the items above,
+//! the [`Sha256Instructions`] trait and the layout of [`BlockWord`] and
+//! [`Sha256Digest`] only change in a breaking release. A chip's internal columns, gates
+//! and region names are not part of the API, so a minor release may change the rows a
+//! digest takes, and with them the verifying key of a circuit using the chip.
 //!
 //! [SHA-256]: https://tools.ietf.org/html/rfc6234
+//! [`Circuit::configure`]: halo2_proofs::plonk::Circuit::configure
+//! [`Circuit::synthesize`]: halo2_proofs::plonk::Circuit::synthesize
 
 use std::cmp::min;
 use std::convert::TryInto;
@@ -8,18 +81,28 @@ use std::fmt;
 
 use halo2_proofs::{
     arithmetic::Field,
-    circuit::{Chip, Layouter},
+    circuit::{AssignedCell, Chip, Layouter},
     plonk::Error,
 };
 
 mod table16;
 
-pub use table16::{BlockWord, Table16Chip, Table16Config};
+pub use table16::{BlockWord, SpreadTableKind, Table16Chip, Table16Config, IV};
+pub use crate::util::{pad_sha256_bytes, sha256_midstate};
+
+use crate::util::{bytes_to_u32_array, u32_array_to_blockwords};
 
 /// The size of a SHA-256 block, in 32-bit words.
 pub const BLOCK_SIZE: usize = 16;
+/// The size of a SHA-256 block, in bytes.
+pub const BLOCK_SIZE_BYTES: usize = BLOCK_SIZE * 4;
 /// The size of a SHA-256 digest, in 32-bit words.
-const DIGEST_SIZE: usize = 8;
+pub const DIGEST_SIZE: usize = 8;
+
+/// Pads `message` and splits it into the big-endian words fed to [`Sha256::digest`].
+pub fn padded_blockwords(message: &[u8]) -> Vec<BlockWord> {
+    u32_array_to_blockwords(&bytes_to_u32_array(&pad_sha256_bytes(message)))
+}
 
 /// The set of circuit instructions required to use the [`Sha256`] gadget.
 pub trait Sha256Instructions<F: Field>: Chip<F> {
@@ -32,13 +115,23 @@ pub trait Sha256Instructions<F: Field>: Chip<F> {
     /// Places the SHA-256 IV in the circuit, returning the initial state variable.
     fn initialization_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error>;
 
-    /// Creates an initial state from the output state of a previous block
+    /// Creates an initial state from the output state of a previous block, constrained to
+    /// equal it.
     fn initialization(
         &self,
         layouter: &mut impl Layouter<F>,
         init_state: &Self::State,
     ) -> Result<Self::State, Error>;
 
+    /// Places an intermediate state in the circuit as constants, returning an
+    /// initialized state variable. The state is the output of compressing a public
+    /// message prefix outside the circuit, so it is part of the circuit's verifying key.
+    fn initialization_from_public_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[u32; DIGEST_SIZE],
+    ) -> Result<Self::State, Error>;
+
     /// Starting from the given initialized state, processes a block of input and returns the
     /// final state.
     fn compress(
@@ -48,12 +141,30 @@ pub trait Sha256Instructions<F: Field>: Chip<F> {
         input: [Self::BlockWord; BLOCK_SIZE],
     ) -> Result<Self::State, Error>;
 
+    /// Starting from the given initialized state, processes a block of input given as
+    /// big-endian assigned bytes and returns the final state. Each byte is range checked
+    /// to 8 bits and constrained to equal the corresponding byte of the message block.
+    fn compress_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: &[AssignedCell<F, F>; BLOCK_SIZE_BYTES],
+    ) -> Result<Self::State, Error>;
+
     /// Converts the given state into a message digest.
     fn digest(
         &self,
         layouter: &mut impl Layouter<F>,
         state: &Self::State,
     ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+
+    /// Converts the given state into a message digest, returning the cells holding its
+    /// words so they can be copied elsewhere or exposed as public inputs.
+    fn assigned_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[AssignedCell<F, F>; DIGEST_SIZE], Error>;
 }
 
 /// The output of a SHA-256 circuit invocation.
@@ -61,12 +172,13 @@ pub trait Sha256Instructions<F: Field>: Chip<F> {
 pub struct Sha256Digest<BlockWord>(pub [BlockWord; DIGEST_SIZE]);
 
 /// A gadget that constrains a SHA-256 invocation. It supports input at a granularity of
-/// 32 bits.
+/// 32 bits, or of assigned bytes.
 #[derive(Debug)]
 pub struct Sha256<F: Field, CS: Sha256Instructions<F>> {
     chip: CS,
     state: CS::State,
     cur_block: Vec<CS::BlockWord>,
+    cur_bytes: Vec<AssignedCell<F, F>>,
     length: usize,
 }
 
@@ -78,16 +190,44 @@ impl<F: Field, Sha256Chip: Sha256Instructions<F>> Sha256<F, Sha256Chip> {
             chip,
             state,
             cur_block: Vec::with_capacity(BLOCK_SIZE),
+            cur_bytes: Vec::with_capacity(BLOCK_SIZE_BYTES),
             length: 0,
         })
     }
 
+    /// Create a hasher instance resuming from `midstate`, the state after compressing
+    /// the first `prefix_len` bytes of the message. The prefix must be a whole number of
+    /// blocks, and public: the midstate is fixed in the circuit.
+    pub fn from_midstate(
+        chip: Sha256Chip,
+        mut layouter: impl Layouter<F>,
+        midstate: &[u32; DIGEST_SIZE],
+        prefix_len: usize,
+    ) -> Result<Self, Error> {
+        if prefix_len % BLOCK_SIZE_BYTES != 0 {
+            return Err(Error::Synthesis);
+        }
+
+        let state = chip.initialization_from_public_state(&mut layouter, midstate)?;
+        Ok(Sha256 {
+            chip,
+            state,
+            cur_block: Vec::with_capacity(BLOCK_SIZE),
+            cur_bytes: Vec::with_capacity(BLOCK_SIZE_BYTES),
+            length: prefix_len * 8,
+        })
+    }
+
     /// Digest data, updating the internal state.
     pub fn update(
         &mut self,
         mut layouter: impl Layouter<F>,
         mut data: &[Sha256Chip::BlockWord],
     ) -> Result<(), Error> {
+        // Word and byte input cannot share a block.
+        if !self.cur_bytes.is_empty() {
+            return Err(Error::Synthesis);
+        }
         self.length += data.len() * 32;
 
         // Fill the current block, if possible.
@@ -129,32 +269,88 @@ impl<F: Field, Sha256Chip: Sha256Instructions<F>> Sha256<F, Sha256Chip> {
         Ok(())
     }
 
-    /// Retrieve result and consume hasher instance.
-    pub fn finalize(
-        mut self,
+    /// Digest assigned bytes, updating the internal state. The bytes are constrained to
+    /// be the message, so they may be shared with other gadgets.
+    ///
+    /// Like [`Sha256::update`], no padding is applied: the bytes fed to the hasher must
+    /// already be padded to a whole number of blocks by the time it is finalized.
+    pub fn update_bytes(
+        &mut self,
         mut layouter: impl Layouter<F>,
-    ) -> Result<Sha256Digest<Sha256Chip::BlockWord>, Error> {
+        data: &[AssignedCell<F, F>],
+    ) -> Result<(), Error> {
+        // Word and byte input cannot share a block.
+        if !self.cur_block.is_empty() {
+            return Err(Error::Synthesis);
+        }
+        self.length += data.len() * 8;
+
+        for byte in data {
+            self.cur_bytes.push(byte.clone());
+            if self.cur_bytes.len() == BLOCK_SIZE_BYTES {
+                self.state = self.chip.compress_bytes(
+                    &mut layouter,
+                    &self.state,
+                    self.cur_bytes[..]
+                        .try_into()
+                        .expect("cur_bytes.len() == BLOCK_SIZE_BYTES"),
+                )?;
+                self.cur_bytes.clear();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compresses the last, partial block.
+    fn flush(&mut self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        // Byte input is never padded in-circuit, so it must end on a block boundary.
+        if !self.cur_bytes.is_empty() {
+            return Err(Error::Synthesis);
+        }
 
         // Pad the remaining block
         if !self.cur_block.is_empty() {
             let padding = vec![Sha256Chip::BlockWord::default(); BLOCK_SIZE - self.cur_block.len()];
             self.cur_block.extend_from_slice(&padding);
-            self.state = self.chip.initialization(&mut layouter, &self.state)?;
+            self.state = self.chip.initialization(layouter, &self.state)?;
             self.state = self.chip.compress(
-                &mut layouter,
+                layouter,
                 &self.state,
                 self.cur_block[..]
                     .try_into()
                     .expect("cur_block.len() == BLOCK_SIZE"),
             )?;
         }
+
+        Ok(())
+    }
+
+    /// Retrieve result and consume hasher instance. The words are values for the prover,
+    /// not bound to the hashed message.
+    pub fn finalize(
+        mut self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<Sha256Digest<Sha256Chip::BlockWord>, Error> {
+        self.flush(&mut layouter)?;
         self.chip
             .digest(&mut layouter, &self.state)
             .map(Sha256Digest)
     }
 
+    /// Retrieve the cells of the result and consume hasher instance. Unlike the words
+    /// of [`Sha256::finalize`], the cells are bound to the hashed message.
+    pub fn finalize_assigned(
+        mut self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<[AssignedCell<F, F>; DIGEST_SIZE], Error> {
+        self.flush(&mut layouter)?;
+        self.chip.assigned_digest(&mut layouter, &self.state)
+    }
+
     /// Convenience function to compute hash of the data. It will handle hasher creation,
-    /// data feeding and finalization.
+    /// data feeding and finalization. Like [`Sha256::finalize`], the words are not
+    /// bound to the data.
     pub fn digest(
         chip: Sha256Chip,
         mut layouter: impl Layouter<F>,
@@ -164,4 +360,174 @@ impl<F: Field, Sha256Chip: Sha256Instructions<F>> Sha256<F, Sha256Chip> {
         hasher.update(layouter.namespace(|| "update"), data)?;
         hasher.finalize(layouter.namespace(|| "finalize"))
     }
+
+    /// Convenience function to compute the hash of assigned bytes, which must already
+    /// be padded to a whole number of blocks.
+    pub fn digest_bytes(
+        chip: Sha256Chip,
+        mut layouter: impl Layouter<F>,
+        data: &[AssignedCell<F, F>],
+    ) -> Result<Sha256Digest<Sha256Chip::BlockWord>, Error> {
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        hasher.update_bytes(layouter.namespace(|| "update"), data)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+
+    /// Like [`Sha256::digest_bytes`], returning the cells of the digest.
+    pub fn digest_bytes_assigned(
+        chip: Sha256Chip,
+        mut layouter: impl Layouter<F>,
+        data: &[AssignedCell<F, F>],
+    ) -> Result<[AssignedCell<F, F>; DIGEST_SIZE], Error> {
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        hasher.update_bytes(layouter.namespace(|| "update"), data)?;
+        hasher.finalize_assigned(layouter.namespace(|| "finalize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        padded_blockwords, Sha256, SpreadTableKind, Table16Chip, Table16Config,
+        BLOCK_SIZE_BYTES,
+    };
+    use crate::layout::measure;
+    use crate::util::{
+        bytes_to_u32_array, pad_sha256_bytes, sha256_hash_bytes_digests, sha256_midstate,
+        u32_array_to_blockwords,
+    };
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2curves::bn256::Fr;
+
+    #[test]
+    fn from_midstate() {
+        struct MyCircuit {
+            message: Vec<u8>,
+        }
+
+        impl Circuit<Fr> for MyCircuit {
+            type Config = Table16Config<Fr>;
+            type FloorPlanner = SimpleFloorPlanner;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {
+                    message: self.message.clone(),
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fr>,
+            ) -> Result<(), Error> {
+                let table16_chip = Table16Chip::construct(config.clone());
+                Table16Chip::load(config, &mut layouter)?;
+
+                // Compress the first block off-circuit, prove only the rest.
+                let padded = pad_sha256_bytes(&self.message);
+                let (prefix, suffix) = padded.split_at(BLOCK_SIZE_BYTES);
+                let midstate = sha256_midstate(prefix);
+
+                let mut hasher = Sha256::from_midstate(
+                    table16_chip,
+                    layouter.namespace(|| "midstate"),
+                    &midstate,
+                    prefix.len(),
+                )?;
+                hasher.update(
+                    layouter.namespace(|| "update"),
+                    &u32_array_to_blockwords(&bytes_to_u32_array(suffix)),
+                )?;
+                let digest = hasher.finalize(layouter.namespace(|| "finalize"))?;
+
+                let expected = bytes_to_u32_array(&sha256_hash_bytes_digests(&self.message));
+                for (word, expected) in digest.0.iter().zip(expected) {
+                    word.0.assert_if_known(|word| *word == expected);
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit = MyCircuit {
+            message: b"eyJhbGciOiJSUzI1NiIsImtpZCI6IjFlOWdkazcifQ.eyJpc3MiOiJodHRwOi8vc2VydmVyLmV4YW1wbGUuY29tIn0".to_vec(),
+        };
+        assert!(circuit.message.len() > BLOCK_SIZE_BYTES);
+
+        let prover = match MockProver::<Fr>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn split_table() {
+        struct MyCircuit {
+            message: Vec<u8>,
+        }
+
+        impl Circuit<Fr> for MyCircuit {
+            type Config = Table16Config<Fr>;
+            type FloorPlanner = SimpleFloorPlanner;
+            #[cfg(feature = "circuit-params")]
+            type Params = ();
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {
+                    message: self.message.clone(),
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+                Table16Chip::configure_with_table(meta, SpreadTableKind::Split)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fr>,
+            ) -> Result<(), Error> {
+                let table16_chip = Table16Chip::construct(config.clone());
+                Table16Chip::load(config, &mut layouter)?;
+
+                let digest = Sha256::digest(
+                    table16_chip,
+                    layouter.namespace(|| "sha256"),
+                    &padded_blockwords(&self.message),
+                )?;
+
+                let expected = bytes_to_u32_array(&sha256_hash_bytes_digests(&self.message));
+                for (word, expected) in digest.0.iter().zip(expected) {
+                    word.0.assert_if_known(|word| *word == expected);
+                }
+
+                Ok(())
+            }
+        }
+
+        let circuit = MyCircuit {
+            message: b"eyJhbGciOiJSUzI1NiIsImtpZCI6IjFlOWdkazcifQ.eyJpc3MiOiJodHRwOi8vc2VydmVyLmV4YW1wbGUuY29tIn0".to_vec(),
+        };
+
+        // Two blocks fit in far fewer rows than a full spread table
+        let k = measure::<Fr, _>(&circuit).unwrap().min_k();
+        assert!(k < 17);
+
+        let prover = match MockProver::<Fr>::run(k, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
 }
@@ -1,15 +1,15 @@
 use super::{
-    super::DIGEST_SIZE,
     util::{i2lebsp, lebs2ip},
-    AssignedBits, BlockWord, SpreadInputs, SpreadVar, Table16Assignment, ROUNDS, STATE,
+    AssignedBits, SpreadInputs, SpreadVar, Table16Assignment, ROUNDS, STATE,
 };
+use ff::PrimeField;
 use halo2_proofs::{
     circuit::{Layouter, Value},
     plonk::{Advice, Column, ConstraintSystem, Error, Selector},
     poly::Rotation,
 };
-use halo2curves::bn256::Fr;
 use std::convert::TryInto;
+use std::marker::PhantomData;
 use std::ops::Range;
 
 mod compression_gates;
@@ -19,6 +19,8 @@ mod subregion_initial;
 mod subregion_main;
 
 use compression_gates::CompressionGate;
+pub(super) use compression_util::dense_halves;
+pub(super) use subregion_digest::AssignedDigest;
 
 pub trait UpperSigmaVar<
     const A_LEN: usize,
@@ -80,16 +82,16 @@ pub trait UpperSigmaVar<
 ///   We align the columns to make it efficient to copy-constrain these forms where they
 ///   are needed.
 #[derive(Clone, Debug)]
-pub struct AbcdVar {
-    a: SpreadVar<2, 4>,
-    b: SpreadVar<11, 22>,
-    c_lo: SpreadVar<3, 6>,
-    c_mid: SpreadVar<3, 6>,
-    c_hi: SpreadVar<3, 6>,
-    d: SpreadVar<10, 20>,
+pub struct AbcdVar<F: PrimeField> {
+    a: SpreadVar<F, 2, 4>,
+    b: SpreadVar<F, 11, 22>,
+    c_lo: SpreadVar<F, 3, 6>,
+    c_mid: SpreadVar<F, 3, 6>,
+    c_hi: SpreadVar<F, 3, 6>,
+    d: SpreadVar<F, 10, 20>,
 }
 
-impl AbcdVar {
+impl<F: PrimeField> AbcdVar<F> {
     fn a_range() -> Range<usize> {
         0..2
     }
@@ -127,7 +129,7 @@ impl AbcdVar {
     }
 }
 
-impl UpperSigmaVar<4, 22, 18, 20> for AbcdVar {
+impl<F: PrimeField> UpperSigmaVar<4, 22, 18, 20> for AbcdVar<F> {
     fn spread_a(&self) -> Value<[bool; 4]> {
         self.a.spread.value().map(|v| v.0)
     }
@@ -169,16 +171,16 @@ impl UpperSigmaVar<4, 22, 18, 20> for AbcdVar {
 ///   We align the columns to make it efficient to copy-constrain these forms where they
 ///   are needed.
 #[derive(Clone, Debug)]
-pub struct EfghVar {
-    a_lo: SpreadVar<3, 6>,
-    a_hi: SpreadVar<3, 6>,
-    b_lo: SpreadVar<2, 4>,
-    b_hi: SpreadVar<3, 6>,
-    c: SpreadVar<14, 28>,
-    d: SpreadVar<7, 14>,
+pub struct EfghVar<F: PrimeField> {
+    a_lo: SpreadVar<F, 3, 6>,
+    a_hi: SpreadVar<F, 3, 6>,
+    b_lo: SpreadVar<F, 2, 4>,
+    b_hi: SpreadVar<F, 3, 6>,
+    c: SpreadVar<F, 14, 28>,
+    d: SpreadVar<F, 7, 14>,
 }
 
-impl EfghVar {
+impl<F: PrimeField> EfghVar<F> {
     fn a_lo_range() -> Range<usize> {
         0..3
     }
@@ -216,7 +218,7 @@ impl EfghVar {
     }
 }
 
-impl UpperSigmaVar<12, 10, 28, 14> for EfghVar {
+impl<F: PrimeField> UpperSigmaVar<12, 10, 28, 14> for EfghVar<F> {
     fn spread_a(&self) -> Value<[bool; 12]> {
         self.a_lo
             .spread
@@ -257,15 +259,15 @@ impl UpperSigmaVar<12, 10, 28, 14> for EfghVar {
 }
 
 #[derive(Clone, Debug)]
-pub struct RoundWordDense(AssignedBits<16>, AssignedBits<16>);
+pub struct RoundWordDense<F: PrimeField>(AssignedBits<F, 16>, AssignedBits<F, 16>);
 
-impl From<(AssignedBits<16>, AssignedBits<16>)> for RoundWordDense {
-    fn from(halves: (AssignedBits<16>, AssignedBits<16>)) -> Self {
+impl<F: PrimeField> From<(AssignedBits<F, 16>, AssignedBits<F, 16>)> for RoundWordDense<F> {
+    fn from(halves: (AssignedBits<F, 16>, AssignedBits<F, 16>)) -> Self {
         Self(halves.0, halves.1)
     }
 }
 
-impl RoundWordDense {
+impl<F: PrimeField> RoundWordDense<F> {
     pub fn value(&self) -> Value<u32> {
         self.0
             .value_u16()
@@ -275,15 +277,15 @@ impl RoundWordDense {
 }
 
 #[derive(Clone, Debug)]
-pub struct RoundWordSpread(AssignedBits<32>, AssignedBits<32>);
+pub struct RoundWordSpread<F: PrimeField>(AssignedBits<F, 32>, AssignedBits<F, 32>);
 
-impl From<(AssignedBits<32>, AssignedBits<32>)> for RoundWordSpread {
-    fn from(halves: (AssignedBits<32>, AssignedBits<32>)) -> Self {
+impl<F: PrimeField> From<(AssignedBits<F, 32>, AssignedBits<F, 32>)> for RoundWordSpread<F> {
+    fn from(halves: (AssignedBits<F, 32>, AssignedBits<F, 32>)) -> Self {
         Self(halves.0, halves.1)
     }
 }
 
-impl RoundWordSpread {
+impl<F: PrimeField> RoundWordSpread<F> {
     pub fn value(&self) -> Value<u64> {
         self.0
             .value_u32()
@@ -293,17 +295,17 @@ impl RoundWordSpread {
 }
 
 #[derive(Clone, Debug)]
-pub struct RoundWordA {
-    pieces: Option<AbcdVar>,
-    dense_halves: RoundWordDense,
-    spread_halves: Option<RoundWordSpread>,
+pub struct RoundWordA<F: PrimeField> {
+    pieces: Option<AbcdVar<F>>,
+    dense_halves: RoundWordDense<F>,
+    spread_halves: Option<RoundWordSpread<F>>,
 }
 
-impl RoundWordA {
+impl<F: PrimeField> RoundWordA<F> {
     pub fn new(
-        pieces: AbcdVar,
-        dense_halves: RoundWordDense,
-        spread_halves: RoundWordSpread,
+        pieces: AbcdVar<F>,
+        dense_halves: RoundWordDense<F>,
+        spread_halves: RoundWordSpread<F>,
     ) -> Self {
         RoundWordA {
             pieces: Some(pieces),
@@ -312,7 +314,7 @@ impl RoundWordA {
         }
     }
 
-    pub fn new_dense(dense_halves: RoundWordDense) -> Self {
+    pub fn new_dense(dense_halves: RoundWordDense<F>) -> Self {
         RoundWordA {
             pieces: None,
             dense_halves,
@@ -322,17 +324,17 @@ impl RoundWordA {
 }
 
 #[derive(Clone, Debug)]
-pub struct RoundWordE {
-    pieces: Option<EfghVar>,
-    dense_halves: RoundWordDense,
-    spread_halves: Option<RoundWordSpread>,
+pub struct RoundWordE<F: PrimeField> {
+    pieces: Option<EfghVar<F>>,
+    dense_halves: RoundWordDense<F>,
+    spread_halves: Option<RoundWordSpread<F>>,
 }
 
-impl RoundWordE {
+impl<F: PrimeField> RoundWordE<F> {
     pub fn new(
-        pieces: EfghVar,
-        dense_halves: RoundWordDense,
-        spread_halves: RoundWordSpread,
+        pieces: EfghVar<F>,
+        dense_halves: RoundWordDense<F>,
+        spread_halves: RoundWordSpread<F>,
     ) -> Self {
         RoundWordE {
             pieces: Some(pieces),
@@ -341,7 +343,7 @@ impl RoundWordE {
         }
     }
 
-    pub fn new_dense(dense_halves: RoundWordDense) -> Self {
+    pub fn new_dense(dense_halves: RoundWordDense<F>) -> Self {
         RoundWordE {
             pieces: None,
             dense_halves,
@@ -351,13 +353,13 @@ impl RoundWordE {
 }
 
 #[derive(Clone, Debug)]
-pub struct RoundWord {
-    dense_halves: RoundWordDense,
-    spread_halves: RoundWordSpread,
+pub struct RoundWord<F: PrimeField> {
+    dense_halves: RoundWordDense<F>,
+    spread_halves: RoundWordSpread<F>,
 }
 
-impl RoundWord {
-    pub fn new(dense_halves: RoundWordDense, spread_halves: RoundWordSpread) -> Self {
+impl<F: PrimeField> RoundWord<F> {
+    pub fn new(dense_halves: RoundWordDense<F>, spread_halves: RoundWordSpread<F>) -> Self {
         RoundWord {
             dense_halves,
             spread_halves,
@@ -367,29 +369,29 @@ impl RoundWord {
 
 /// The internal state for SHA-256.
 #[derive(Clone, Debug)]
-pub struct State {
-    a: Option<StateWord>,
-    b: Option<StateWord>,
-    c: Option<StateWord>,
-    d: Option<StateWord>,
-    e: Option<StateWord>,
-    f: Option<StateWord>,
-    g: Option<StateWord>,
-    h: Option<StateWord>,
+pub struct State<F: PrimeField> {
+    a: Option<StateWord<F>>,
+    b: Option<StateWord<F>>,
+    c: Option<StateWord<F>>,
+    d: Option<StateWord<F>>,
+    e: Option<StateWord<F>>,
+    f: Option<StateWord<F>>,
+    g: Option<StateWord<F>>,
+    h: Option<StateWord<F>>,
 }
 
-impl State {
+impl<F: PrimeField> State<F> {
     #[allow(clippy::many_single_char_names)]
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        a: StateWord,
-        b: StateWord,
-        c: StateWord,
-        d: StateWord,
-        e: StateWord,
-        f: StateWord,
-        g: StateWord,
-        h: StateWord,
+        a: StateWord<F>,
+        b: StateWord<F>,
+        c: StateWord<F>,
+        d: StateWord<F>,
+        e: StateWord<F>,
+        f: StateWord<F>,
+        g: StateWord<F>,
+        h: StateWord<F>,
     ) -> Self {
         State {
             a: Some(a),
@@ -418,19 +420,19 @@ impl State {
 }
 
 #[derive(Clone, Debug)]
-pub enum StateWord {
-    A(RoundWordA),
-    B(RoundWord),
-    C(RoundWord),
-    D(RoundWordDense),
-    E(RoundWordE),
-    F(RoundWord),
-    G(RoundWord),
-    H(RoundWordDense),
+pub enum StateWord<F: PrimeField> {
+    A(RoundWordA<F>),
+    B(RoundWord<F>),
+    C(RoundWord<F>),
+    D(RoundWordDense<F>),
+    E(RoundWordE<F>),
+    F(RoundWord<F>),
+    G(RoundWord<F>),
+    H(RoundWordDense<F>),
 }
 
 #[derive(Clone, Debug)]
-pub(super) struct CompressionConfig {
+pub(super) struct CompressionConfig<F: PrimeField> {
     lookup: SpreadInputs,
     message_schedule: Column<Advice>,
     extras: [Column<Advice>; 6],
@@ -451,13 +453,15 @@ pub(super) struct CompressionConfig {
     s_decompose_efgh: Selector,
 
     s_digest: Selector,
+    s_feed_forward: Selector,
+    _marker: PhantomData<F>,
 }
 
-impl Table16Assignment for CompressionConfig {}
+impl<F: PrimeField> Table16Assignment<F> for CompressionConfig<F> {}
 
-impl CompressionConfig {
+impl<F: PrimeField> CompressionConfig<F> {
     pub(super) fn configure(
-        meta: &mut ConstraintSystem<Fr>,
+        meta: &mut ConstraintSystem<F>,
         lookup: SpreadInputs,
         message_schedule: Column<Advice>,
         extras: [Column<Advice>; 6],
@@ -478,6 +482,7 @@ impl CompressionConfig {
         let s_decompose_efgh = meta.selector();
 
         let s_digest = meta.selector();
+        let s_feed_forward = meta.selector();
 
         // Rename these here for ease of matching the gates to the specification.
         let a_0 = lookup.tag;
@@ -841,6 +846,29 @@ impl CompressionConfig {
             )
         });
 
+        // s_feed_forward adds the state a block started from onto its compressed state
+        meta.create_gate("s_feed_forward", |meta| {
+            let s_feed_forward = meta.query_selector(s_feed_forward);
+            let sum_lo = meta.query_advice(a_1, Rotation::cur());
+            let sum_hi = meta.query_advice(a_1, Rotation::next());
+            let sum_carry = meta.query_advice(a_9, Rotation::cur());
+            let x_lo = meta.query_advice(a_3, Rotation::cur());
+            let x_hi = meta.query_advice(a_3, Rotation::next());
+            let y_lo = meta.query_advice(a_4, Rotation::cur());
+            let y_hi = meta.query_advice(a_4, Rotation::next());
+
+            CompressionGate::s_feed_forward(
+                s_feed_forward,
+                sum_lo,
+                sum_hi,
+                sum_carry,
+                x_lo,
+                x_hi,
+                y_lo,
+                y_hi,
+            )
+        });
+
         CompressionConfig {
             lookup,
             message_schedule,
@@ -856,6 +884,8 @@ impl CompressionConfig {
             s_decompose_abcd,
             s_decompose_efgh,
             s_digest,
+            s_feed_forward,
+            _marker: PhantomData,
         }
     }
 
@@ -863,9 +893,9 @@ impl CompressionConfig {
     /// Returns an initialized state.
     pub(super) fn initialize_with_iv(
         &self,
-        layouter: &mut impl Layouter<Fr>,
+        layouter: &mut impl Layouter<F>,
         init_state: [u32; STATE],
-    ) -> Result<State, Error> {
+    ) -> Result<State<F>, Error> {
         let mut new_state = State::empty_state();
         layouter.assign_region(
             || "initialize_with_iv",
@@ -877,13 +907,31 @@ impl CompressionConfig {
         Ok(new_state)
     }
 
+    /// Initialize compression with a constant intermediate state of 32-bit words, the
+    /// output of compressing a public message prefix. Returns an initialized state.
+    pub(super) fn initialize_with_midstate(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        init_state: [u32; STATE],
+    ) -> Result<State<F>, Error> {
+        let mut new_state = State::empty_state();
+        layouter.assign_region(
+            || "initialize_with_midstate",
+            |mut region| {
+                new_state = self.initialize_iv(&mut region, init_state)?;
+                Ok(())
+            },
+        )?;
+        Ok(new_state)
+    }
+
     /// Initialize compression with some initialized state. This could be a state
     /// output from a previous compression round.
     pub(super) fn initialize_with_state(
         &self,
-        layouter: &mut impl Layouter<Fr>,
-        init_state: State,
-    ) -> Result<State, Error> {
+        layouter: &mut impl Layouter<F>,
+        init_state: State<F>,
+    ) -> Result<State<F>, Error> {
         let mut new_state = State::empty_state();
         layouter.assign_region(
             || "initialize_with_state",
@@ -895,32 +943,41 @@ impl CompressionConfig {
         Ok(new_state)
     }
 
-    /// Initialize compression with a constant Initialization Vector of 32-byte words.
-    /// Returns an initialized state.
+    /// Add `state2`, the state a block started from, onto `state`, its compressed
+    /// state, word by word modulo 2^32. Returns the sums as an initialized state, to
+    /// compress the next block or output the digest.
     pub(super) fn add_state_to_state(
         &self,
-        layouter: &mut impl Layouter<Fr>,
-        state: State,
-        state2: State,
-    ) -> Result<State, Error> {
-        let mut new_state = State::empty_state();
+        layouter: &mut impl Layouter<F>,
+        state: State<F>,
+        state2: State<F>,
+    ) -> Result<State<F>, Error> {
+        let x = dense_halves(state);
+        let y = dense_halves(state2);
+        let sums = std::array::from_fn(|idx| {
+            x[idx]
+                .value()
+                .zip(y[idx].value())
+                .map(|(x, y)| x.wrapping_add(y))
+        });
+
+        let sums = layouter.assign_region(
+            || "feed_forward",
+            |mut region| self.assign_feed_forward(&mut region, &x, &y, sums),
+        )?;
         layouter.assign_region(
             || "add_iv_to_state",
-            |mut region| {
-                new_state = self.add_state_onto_state(&mut region, state.clone(), state2.clone())?;
-                Ok(())
-            },
-        )?;
-        Ok(new_state)
+            |mut region| self.initialize_dense(&mut region, &sums),
+        )
     }
 
     /// Given an initialized state and a message schedule, perform 64 compression rounds.
     pub(super) fn compress(
         &self,
-        layouter: &mut impl Layouter<Fr>,
-        initialized_state: State,
-        w_halves: [(AssignedBits<16>, AssignedBits<16>); ROUNDS],
-    ) -> Result<State, Error> {
+        layouter: &mut impl Layouter<F>,
+        initialized_state: State<F>,
+        w_halves: [(AssignedBits<F, 16>, AssignedBits<F, 16>); ROUNDS],
+    ) -> Result<State<F>, Error> {
         let mut state = State::empty_state();
         layouter.assign_region(
             || "compress",
@@ -935,22 +992,17 @@ impl CompressionConfig {
         Ok(state)
     }
 
-    /// After the final round, convert the state into the final digest.
+    /// After the final round, convert the state into the final digest, along with the
+    /// cells holding its words.
     pub(super) fn digest(
         &self,
-        layouter: &mut impl Layouter<Fr>,
-        state: State,
-    ) -> Result<[BlockWord; DIGEST_SIZE], Error> {
-        let mut digest = [BlockWord(Value::known(0)); DIGEST_SIZE];
+        layouter: &mut impl Layouter<F>,
+        state: State<F>,
+    ) -> Result<AssignedDigest<F>, Error> {
         layouter.assign_region(
             || "digest",
-            |mut region| {
-                digest = self.assign_digest(&mut region, state.clone())?;
-
-                Ok(())
-            },
-        )?;
-        Ok(digest)
+            |mut region| self.assign_digest(&mut region, state.clone()),
+        )
     }
 }
 
@@ -958,69 +1010,238 @@ impl CompressionConfig {
 mod tests {
     use super::super::{
         super::BLOCK_SIZE, msg_schedule_test_input, BlockWord, Table16Chip, Table16Config, IV,
+        STATE,
     };
+    use super::compression_util::dense_halves;
+    use ff::PrimeField;
     use halo2_proofs::{
-        circuit::{Layouter, SimpleFloorPlanner},
+        circuit::{Layouter, SimpleFloorPlanner, Value},
         dev::MockProver,
         plonk::{Circuit, ConstraintSystem, Error},
     };
-    use halo2curves::bn256::Fr;
+    use halo2curves::{bn256::Fr, pasta::Fp};
+    use std::marker::PhantomData;
+
+    struct MyCircuit<F: PrimeField>(PhantomData<F>);
+
+    impl<F: PrimeField> Circuit<F> for MyCircuit<F> {
+        type Config = Table16Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            MyCircuit(PhantomData)
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            Table16Chip::load(config.clone(), &mut layouter)?;
+
+            // Test vector: "abc"
+            let input: [BlockWord; BLOCK_SIZE] = msg_schedule_test_input();
+
+            let (_, w_halves) = config.message_schedule.process(&mut layouter, input)?;
+
+            let compression = config.compression.clone();
+            let initial_state = compression.initialize_with_iv(&mut layouter, IV)?;
+
+            let state = config
+                .compression
+                .compress(&mut layouter, initial_state, w_halves)?;
+
+            let (digest, _) = config.compression.digest(&mut layouter, state)?;
+            for (idx, digest_word) in digest.iter().enumerate() {
+                digest_word.0.assert_if_known(|digest_word| {
+                    (*digest_word as u64 + IV[idx] as u64) as u32
+                        == super::compression_util::COMPRESSION_OUTPUT[idx]
+                });
+            }
+
+            Ok(())
+        }
+    }
 
     #[test]
     fn compress() {
-        struct MyCircuit {}
+        let circuit: MyCircuit<Fr> = MyCircuit(PhantomData);
 
-        impl Circuit<Fr> for MyCircuit {
-            type Config = Table16Config;
-            type FloorPlanner = SimpleFloorPlanner;
-            #[cfg(feature = "circuit-params")]
-            type Params = ();
+        let prover = match MockProver::<Fr>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
 
-            fn without_witnesses(&self) -> Self {
-                MyCircuit {}
-            }
+    #[test]
+    fn compress_pasta() {
+        let circuit: MyCircuit<Fp> = MyCircuit(PhantomData);
 
-            fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
-                Table16Chip::configure(meta)
-            }
+        let prover = match MockProver::<Fp>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
 
-            fn synthesize(
-                &self,
-                config: Self::Config,
-                mut layouter: impl Layouter<Fr>,
-            ) -> Result<(), Error> {
-                Table16Chip::load(config.clone(), &mut layouter)?;
+    /// Witnesses `state` and constrains it to the IV.
+    struct ConstantState {
+        state: [u32; STATE],
+    }
 
-                // Test vector: "abc"
-                let input: [BlockWord; BLOCK_SIZE] = msg_schedule_test_input();
+    impl Circuit<Fr> for ConstantState {
+        type Config = Table16Config<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
 
-                let (_, w_halves) = config.message_schedule.process(&mut layouter, input)?;
+        fn without_witnesses(&self) -> Self {
+            ConstantState { state: self.state }
+        }
 
-                let compression = config.compression.clone();
-                let initial_state = compression.initialize_with_iv(&mut layouter, IV)?;
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            Table16Chip::load(config.clone(), &mut layouter)?;
+
+            layouter.assign_region(
+                || "constant state",
+                |mut region| {
+                    let state = config
+                        .compression
+                        .assign_state(&mut region, self.state.map(Value::known))?;
+                    config
+                        .compression
+                        .constrain_state_constant(&mut region, &state, IV)
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn wrong_midstate_is_rejected() {
+        let prover = MockProver::<Fr>::run(17, &ConstantState { state: IV }, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A, D and H take different paths through the initial region.
+        for idx in [0, 3, 7] {
+            let mut state = IV;
+            state[idx] ^= 1 << 20;
+            let prover = MockProver::<Fr>::run(17, &ConstantState { state }, vec![]).unwrap();
+            assert!(prover.verify().is_err(), "word {} was not bound", idx);
+        }
+    }
 
-                let state = config
-                    .compression
-                    .compress(&mut layouter, initial_state, w_halves)?;
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Tamper {
+        None,
+        FeedForward,
+        Chain,
+    }
 
-                let digest = config.compression.digest(&mut layouter, state)?;
+    /// Compresses the "abc" block, feeds the IV forward and chains the sums into the
+    /// state the digest is read from, tampering with one of the witnesses.
+    struct FeedForward(Tamper);
+
+    impl Circuit<Fr> for FeedForward {
+        type Config = Table16Config<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            FeedForward(self.0)
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            Table16Chip::load(config.clone(), &mut layouter)?;
+
+            let input: [BlockWord; BLOCK_SIZE] = msg_schedule_test_input();
+            let (_, w_halves) = config.message_schedule.process(&mut layouter, input)?;
+
+            let compression = config.compression.clone();
+            let initial_state = compression.initialize_with_iv(&mut layouter, IV)?;
+            let state = compression.compress(&mut layouter, initial_state.clone(), w_halves)?;
+
+            let x = dense_halves(state);
+            let y = dense_halves(initial_state);
+            let mut sums: [Value<u32>; STATE] = std::array::from_fn(|idx| {
+                x[idx]
+                    .value()
+                    .zip(y[idx].value())
+                    .map(|(x, y)| x.wrapping_add(y))
+            });
+            if self.0 == Tamper::FeedForward {
+                sums[3] = sums[3].map(|sum| sum.wrapping_add(1));
+            }
+            let sums = layouter.assign_region(
+                || "feed_forward",
+                |mut region| compression.assign_feed_forward(&mut region, &x, &y, sums),
+            )?;
+
+            let mut words: [Value<u32>; STATE] = std::array::from_fn(|idx| sums[idx].value());
+            if self.0 == Tamper::Chain {
+                words[7] = words[7].map(|word| word ^ 1);
+            }
+            let state = layouter.assign_region(
+                || "chain",
+                |mut region| {
+                    let state = compression.assign_state(&mut region, words)?;
+                    compression.constrain_state_equal(&mut region, &state, &sums)?;
+                    Ok(state)
+                },
+            )?;
+
+            let (digest, _) = compression.digest(&mut layouter, state)?;
+            if self.0 == Tamper::None {
                 for (idx, digest_word) in digest.iter().enumerate() {
                     digest_word.0.assert_if_known(|digest_word| {
-                        (*digest_word as u64 + IV[idx] as u64) as u32
-                            == super::compression_util::COMPRESSION_OUTPUT[idx]
+                        *digest_word == super::compression_util::COMPRESSION_OUTPUT[idx]
                     });
                 }
-
-                Ok(())
             }
-        }
 
-        let circuit: MyCircuit = MyCircuit {};
+            Ok(())
+        }
+    }
 
-        let prover = match MockProver::<Fr>::run(17, &circuit, vec![]) {
-            Ok(prover) => prover,
-            Err(e) => panic!("{:?}", e),
-        };
+    #[test]
+    fn feed_forward() {
+        let prover = MockProver::<Fr>::run(17, &FeedForward(Tamper::None), vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[test]
+    fn wrong_feed_forward_is_rejected() {
+        let prover =
+            MockProver::<Fr>::run(17, &FeedForward(Tamper::FeedForward), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn wrong_chained_state_is_rejected() {
+        let prover = MockProver::<Fr>::run(17, &FeedForward(Tamper::Chain), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
@@ -408,6 +408,34 @@ impl<F: PrimeField> CompressionGate<F> {
         Some(("s_e_new", s_e_new * check))
     }
 
+    // s_feed_forward to get the word x + y of the next state, with a boolean carry
+    #[allow(clippy::too_many_arguments)]
+    pub fn s_feed_forward(
+        s_feed_forward: Expression<F>,
+        sum_lo: Expression<F>,
+        sum_hi: Expression<F>,
+        sum_carry: Expression<F>,
+        x_lo: Expression<F>,
+        x_hi: Expression<F>,
+        y_lo: Expression<F>,
+        y_hi: Expression<F>,
+    ) -> impl IntoIterator<Item = Constraint<F>> {
+        let lo = x_lo + y_lo;
+        let hi = x_hi + y_hi;
+        let sum = lo + hi * F::from(1 << 16);
+        let word = sum_lo + sum_hi * F::from(1 << 16);
+
+        let check = sum - (sum_carry.clone() * F::from(1 << 32)) - word;
+
+        Constraints::with_selector(
+            s_feed_forward,
+            [
+                ("s_feed_forward", check),
+                ("carry_range", Gate::range_check(sum_carry, 0, 1)),
+            ],
+        )
+    }
+
     // s_digest on final round
     #[allow(clippy::too_many_arguments)]
     pub fn s_digest(
@@ -2,14 +2,15 @@ use super::{
     AbcdVar, CompressionConfig, EfghVar, RoundWord, RoundWordA, RoundWordDense, RoundWordE,
     RoundWordSpread, State, UpperSigmaVar,
 };
+use crate::sha256::table16::STATE;
 use crate::sha256::table16::{
     util::*, AssignedBits, SpreadVar, SpreadWord, StateWord, Table16Assignment,
 };
+use ff::PrimeField;
 use halo2_proofs::{
     circuit::{Region, Value},
     plonk::{Advice, Column, Error},
 };
-use halo2curves::bn256::Fr;
 use std::convert::TryInto;
 
 // Test vector 'abc'
@@ -198,13 +199,13 @@ pub fn get_digest_efgh_row() -> usize {
     get_digest_abcd_row() + 2
 }
 
-impl CompressionConfig {
+impl<F: PrimeField> CompressionConfig<F> {
     pub(super) fn decompose_abcd(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         row: usize,
         val: Value<u32>,
-    ) -> Result<AbcdVar, Error> {
+    ) -> Result<AbcdVar<F>, Error> {
         self.s_decompose_abcd.enable(region, row)?;
 
         let a_3 = self.extras[0];
@@ -212,7 +213,7 @@ impl CompressionConfig {
         let a_5 = self.message_schedule;
         let a_6 = self.extras[2];
 
-        let spread_pieces = val.map(AbcdVar::pieces);
+        let spread_pieces = val.map(AbcdVar::<F>::pieces);
         let spread_pieces = spread_pieces.transpose_vec(6);
 
         let a = SpreadVar::without_lookup(
@@ -272,10 +273,10 @@ impl CompressionConfig {
 
     pub(super) fn decompose_efgh(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         row: usize,
         val: Value<u32>,
-    ) -> Result<EfghVar, Error> {
+    ) -> Result<EfghVar<F>, Error> {
         self.s_decompose_efgh.enable(region, row)?;
 
         let a_3 = self.extras[0];
@@ -283,7 +284,7 @@ impl CompressionConfig {
         let a_5 = self.message_schedule;
         let a_6 = self.extras[2];
 
-        let spread_pieces = val.map(EfghVar::pieces);
+        let spread_pieces = val.map(EfghVar::<F>::pieces);
         let spread_pieces = spread_pieces.transpose_vec(6);
 
         let a_lo = SpreadVar::without_lookup(
@@ -343,10 +344,10 @@ impl CompressionConfig {
 
     pub(super) fn decompose_a(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: RoundIdx,
         a_val: Value<u32>,
-    ) -> Result<RoundWordA, Error> {
+    ) -> Result<RoundWordA<F>, Error> {
         let row = get_decompose_a_row(round_idx);
 
         let (dense_halves, spread_halves) = self.assign_word_halves(region, row, a_val)?;
@@ -356,10 +357,10 @@ impl CompressionConfig {
 
     pub(super) fn decompose_e(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: RoundIdx,
         e_val: Value<u32>,
-    ) -> Result<RoundWordE, Error> {
+    ) -> Result<RoundWordE<F>, Error> {
         let row = get_decompose_e_row(round_idx);
 
         let (dense_halves, spread_halves) = self.assign_word_halves(region, row, e_val)?;
@@ -369,10 +370,10 @@ impl CompressionConfig {
 
     pub(super) fn assign_upper_sigma_0(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: MainRoundIdx,
-        word: AbcdVar,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        word: AbcdVar<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         // Rename these here for ease of matching the gates to the specification.
         let a_3 = self.extras[0];
         let a_4 = self.extras[1];
@@ -427,10 +428,10 @@ impl CompressionConfig {
 
     pub(super) fn assign_upper_sigma_1(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: MainRoundIdx,
-        word: EfghVar,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        word: EfghVar<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         // Rename these here for ease of matching the gates to the specification.
         let a_3 = self.extras[0];
         let a_4 = self.extras[1];
@@ -486,13 +487,13 @@ impl CompressionConfig {
 
     fn assign_ch_outputs(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         row: usize,
         r_0_even: Value<[bool; 16]>,
         r_0_odd: Value<[bool; 16]>,
         r_1_even: Value<[bool; 16]>,
         r_1_odd: Value<[bool; 16]>,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let a_3 = self.extras[0];
 
         let (_even, odd) = self.assign_spread_outputs(
@@ -511,11 +512,11 @@ impl CompressionConfig {
 
     pub(super) fn assign_ch(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: MainRoundIdx,
-        spread_halves_e: RoundWordSpread,
-        spread_halves_f: RoundWordSpread,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        spread_halves_e: RoundWordSpread<F>,
+        spread_halves_f: RoundWordSpread<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let a_3 = self.extras[0];
         let a_4 = self.extras[1];
 
@@ -557,11 +558,11 @@ impl CompressionConfig {
 
     pub(super) fn assign_ch_neg(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: MainRoundIdx,
-        spread_halves_e: RoundWordSpread,
-        spread_halves_g: RoundWordSpread,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        spread_halves_e: RoundWordSpread<F>,
+        spread_halves_g: RoundWordSpread<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let row = get_ch_neg_row(round_idx);
 
         self.s_ch_neg.enable(region, row)?;
@@ -592,7 +593,7 @@ impl CompressionConfig {
             .value()
             .map(|spread_e_lo| negate_spread(spread_e_lo.0));
         // Assign spread_neg_e_lo
-        AssignedBits::<32>::assign_bits(
+        AssignedBits::<F, 32>::assign_bits(
             region,
             || "spread_neg_e_lo",
             a_3,
@@ -606,7 +607,7 @@ impl CompressionConfig {
             .value()
             .map(|spread_e_hi| negate_spread(spread_e_hi.0));
         // Assign spread_neg_e_hi
-        AssignedBits::<32>::assign_bits(
+        AssignedBits::<F, 32>::assign_bits(
             region,
             || "spread_neg_e_hi",
             a_4,
@@ -636,13 +637,13 @@ impl CompressionConfig {
 
     fn assign_maj_outputs(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         row: usize,
         r_0_even: Value<[bool; 16]>,
         r_0_odd: Value<[bool; 16]>,
         r_1_even: Value<[bool; 16]>,
         r_1_odd: Value<[bool; 16]>,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let a_3 = self.extras[0];
         let (_even, odd) = self.assign_spread_outputs(
             region,
@@ -660,12 +661,12 @@ impl CompressionConfig {
 
     pub(super) fn assign_maj(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: MainRoundIdx,
-        spread_halves_a: RoundWordSpread,
-        spread_halves_b: RoundWordSpread,
-        spread_halves_c: RoundWordSpread,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+        spread_halves_a: RoundWordSpread<F>,
+        spread_halves_b: RoundWordSpread<F>,
+        spread_halves_c: RoundWordSpread<F>,
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let a_4 = self.extras[1];
         let a_5 = self.message_schedule;
 
@@ -718,15 +719,15 @@ impl CompressionConfig {
     #[allow(clippy::too_many_arguments)]
     pub(super) fn assign_h_prime(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: MainRoundIdx,
-        h: RoundWordDense,
-        ch: (AssignedBits<16>, AssignedBits<16>),
-        ch_neg: (AssignedBits<16>, AssignedBits<16>),
-        sigma_1: (AssignedBits<16>, AssignedBits<16>),
+        h: RoundWordDense<F>,
+        ch: (AssignedBits<F, 16>, AssignedBits<F, 16>),
+        ch_neg: (AssignedBits<F, 16>, AssignedBits<F, 16>),
+        sigma_1: (AssignedBits<F, 16>, AssignedBits<F, 16>),
         k: u32,
-        w: &(AssignedBits<16>, AssignedBits<16>),
-    ) -> Result<RoundWordDense, Error> {
+        w: &(AssignedBits<F, 16>, AssignedBits<F, 16>),
+    ) -> Result<RoundWordDense<F>, Error> {
         let row = get_h_prime_row(round_idx);
         self.s_h_prime.enable(region, row)?;
 
@@ -750,8 +751,8 @@ impl CompressionConfig {
         let k_lo: [bool; 16] = k[..16].try_into().unwrap();
         let k_hi: [bool; 16] = k[16..].try_into().unwrap();
         {
-            AssignedBits::<16>::assign_bits(region, || "k_lo", a_6, row - 1, Value::known(k_lo))?;
-            AssignedBits::<16>::assign_bits(region, || "k_hi", a_6, row, Value::known(k_hi))?;
+            AssignedBits::<F, 16>::assign_bits(region, || "k_lo", a_6, row - 1, Value::known(k_lo))?;
+            AssignedBits::<F, 16>::assign_bits(region, || "k_hi", a_6, row, Value::known(k_hi))?;
         }
 
         // Assign and copy w
@@ -783,7 +784,7 @@ impl CompressionConfig {
                 || "h_prime_carry",
                 a_9,
                 row + 1,
-                || h_prime_carry.map(|value| Fr::from(value as u64)),
+                || h_prime_carry.map(|value| F::from(value as u64)),
             )?;
 
             let h_prime: Value<[bool; 32]> = h_prime.map(|w| i2lebsp(w.into()));
@@ -791,9 +792,9 @@ impl CompressionConfig {
             let h_prime_hi: Value<[bool; 16]> = h_prime.map(|w| w[16..].try_into().unwrap());
 
             let h_prime_lo =
-                AssignedBits::<16>::assign_bits(region, || "h_prime_lo", a_7, row + 1, h_prime_lo)?;
+                AssignedBits::<F, 16>::assign_bits(region, || "h_prime_lo", a_7, row + 1, h_prime_lo)?;
             let h_prime_hi =
-                AssignedBits::<16>::assign_bits(region, || "h_prime_hi", a_8, row + 1, h_prime_hi)?;
+                AssignedBits::<F, 16>::assign_bits(region, || "h_prime_hi", a_8, row + 1, h_prime_hi)?;
 
             Ok((h_prime_lo, h_prime_hi).into())
         }
@@ -802,11 +803,11 @@ impl CompressionConfig {
     // s_e_new to get E_new = H' + D
     pub(super) fn assign_e_new(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: MainRoundIdx,
-        d: &RoundWordDense,
-        h_prime: &RoundWordDense,
-    ) -> Result<RoundWordDense, Error> {
+        d: &RoundWordDense<F>,
+        h_prime: &RoundWordDense<F>,
+    ) -> Result<RoundWordDense<F>, Error> {
         let row = get_e_new_row(round_idx);
 
         self.s_e_new.enable(region, row)?;
@@ -830,7 +831,7 @@ impl CompressionConfig {
             || "e_new_carry",
             a_9,
             row + 1,
-            || e_new_carry.map(Fr::from),
+            || e_new_carry.map(F::from),
         )?;
 
         Ok(e_new_dense)
@@ -839,12 +840,12 @@ impl CompressionConfig {
     // s_a_new to get A_new = H' + Maj(A, B, C) + s_upper_sigma_0(A)
     pub(super) fn assign_a_new(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: MainRoundIdx,
-        maj: (AssignedBits<16>, AssignedBits<16>),
-        sigma_0: (AssignedBits<16>, AssignedBits<16>),
-        h_prime: RoundWordDense,
-    ) -> Result<RoundWordDense, Error> {
+        maj: (AssignedBits<F, 16>, AssignedBits<F, 16>),
+        sigma_0: (AssignedBits<F, 16>, AssignedBits<F, 16>),
+        h_prime: RoundWordDense<F>,
+    ) -> Result<RoundWordDense<F>, Error> {
         let row = get_a_new_row(round_idx);
 
         self.s_a_new.enable(region, row)?;
@@ -884,7 +885,7 @@ impl CompressionConfig {
             || "a_new_carry",
             a_9,
             row,
-            || a_new_carry.map(Fr::from),
+            || a_new_carry.map(F::from),
         )?;
 
         Ok(a_new_dense)
@@ -892,23 +893,23 @@ impl CompressionConfig {
 
     pub fn assign_word_halves_dense(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         lo_row: usize,
         lo_col: Column<Advice>,
         hi_row: usize,
         hi_col: Column<Advice>,
         word: Value<u32>,
-    ) -> Result<RoundWordDense, Error> {
+    ) -> Result<RoundWordDense<F>, Error> {
         let word: Value<[bool; 32]> = word.map(|w| i2lebsp(w.into()));
 
         let lo = {
             let lo: Value<[bool; 16]> = word.map(|w| w[..16].try_into().unwrap());
-            AssignedBits::<16>::assign_bits(region, || "lo", lo_col, lo_row, lo)?
+            AssignedBits::<F, 16>::assign_bits(region, || "lo", lo_col, lo_row, lo)?
         };
 
         let hi = {
             let hi: Value<[bool; 16]> = word.map(|w| w[16..].try_into().unwrap());
-            AssignedBits::<16>::assign_bits(region, || "hi", hi_col, hi_row, hi)?
+            AssignedBits::<F, 16>::assign_bits(region, || "hi", hi_col, hi_row, hi)?
         };
 
         Ok((lo, hi).into())
@@ -918,10 +919,10 @@ impl CompressionConfig {
     #[allow(clippy::type_complexity)]
     pub fn assign_word_halves(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         row: usize,
         word: Value<u32>,
-    ) -> Result<(RoundWordDense, RoundWordSpread), Error> {
+    ) -> Result<(RoundWordDense<F>, RoundWordSpread<F>), Error> {
         // Rename these here for ease of matching the gates to the specification.
         let a_7 = self.extras[3];
         let a_8 = self.extras[4];
@@ -942,17 +943,17 @@ impl CompressionConfig {
 }
 
 #[allow(clippy::many_single_char_names)]
-pub fn match_state(
-    state: State,
+pub fn match_state<F: PrimeField>(
+    state: State<F>,
 ) -> (
-    RoundWordA,
-    RoundWord,
-    RoundWord,
-    RoundWordDense,
-    RoundWordE,
-    RoundWord,
-    RoundWord,
-    RoundWordDense,
+    RoundWordA<F>,
+    RoundWord<F>,
+    RoundWord<F>,
+    RoundWordDense<F>,
+    RoundWordE<F>,
+    RoundWord<F>,
+    RoundWord<F>,
+    RoundWordDense<F>,
 ) {
     let a = match state.a {
         Some(StateWord::A(a)) => a,
@@ -989,3 +990,18 @@ pub fn match_state(
 
     (a, b, c, d, e, f, g, h)
 }
+
+/// The dense halves of each word of `state`.
+pub fn dense_halves<F: PrimeField>(state: State<F>) -> [RoundWordDense<F>; STATE] {
+    let (a, b, c, d, e, f, g, h) = match_state(state);
+    [
+        a.dense_halves,
+        b.dense_halves,
+        c.dense_halves,
+        d,
+        e.dense_halves,
+        f.dense_halves,
+        g.dense_halves,
+        h,
+    ]
+}
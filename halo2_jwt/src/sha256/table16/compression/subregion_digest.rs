@@ -1,18 +1,21 @@
 use super::super::{super::DIGEST_SIZE, BlockWord, RoundWordDense};
 use super::{compression_util::*, CompressionConfig, State};
+use ff::PrimeField;
 use halo2_proofs::{
-    circuit::{Region, Value},
+    circuit::{AssignedCell, Region, Value},
     plonk::{Advice, Column, Error},
 };
-use halo2curves::bn256::Fr;
 
-impl CompressionConfig {
+/// The digest words, along with the cells the `s_digest` gate constrains them in.
+pub type AssignedDigest<F> = ([BlockWord; DIGEST_SIZE], [AssignedCell<F, F>; DIGEST_SIZE]);
+
+impl<F: PrimeField> CompressionConfig<F> {
     #[allow(clippy::many_single_char_names)]
     pub fn assign_digest(
         &self,
-        region: &mut Region<'_, Fr>,
-        state: State,
-    ) -> Result<[BlockWord; DIGEST_SIZE], Error> {
+        region: &mut Region<'_, F>,
+        state: State<F>,
+    ) -> Result<AssignedDigest<F>, Error> {
         let a_3 = self.extras[0];
         let a_4 = self.extras[1];
         let a_5 = self.message_schedule;
@@ -35,16 +38,16 @@ impl CompressionConfig {
             .1
             .copy_advice(|| "a_hi", region, a_4, abcd_row)?;
         let a = a.dense_halves.value();
-        region.assign_advice(
+        let a_cell = region.assign_advice(
             || "a",
             a_5,
             abcd_row,
-            || a.map(|a| Fr::from(a as u64)),
+            || a.map(|a| F::from(a as u64)),
         )?;
 
-        let b = self.assign_digest_word(region, abcd_row, a_6, a_7, a_8, b.dense_halves)?;
-        let c = self.assign_digest_word(region, abcd_row + 1, a_3, a_4, a_5, c.dense_halves)?;
-        let d = self.assign_digest_word(region, abcd_row + 1, a_6, a_7, a_8, d)?;
+        let (b, b_cell) = self.assign_digest_word(region, abcd_row, a_6, a_7, a_8, b.dense_halves)?;
+        let (c, c_cell) = self.assign_digest_word(region, abcd_row + 1, a_3, a_4, a_5, c.dense_halves)?;
+        let (d, d_cell) = self.assign_digest_word(region, abcd_row + 1, a_6, a_7, a_8, d)?;
 
         // Assign digest for E, F, G, H
         e.dense_halves
@@ -54,49 +57,52 @@ impl CompressionConfig {
             .1
             .copy_advice(|| "e_hi", region, a_4, efgh_row)?;
         let e = e.dense_halves.value();
-        region.assign_advice(
+        let e_cell = region.assign_advice(
             || "e",
             a_5,
             efgh_row,
-            || e.map(|e| Fr::from(e as u64)),
+            || e.map(|e| F::from(e as u64)),
         )?;
 
-        let f = self.assign_digest_word(region, efgh_row, a_6, a_7, a_8, f.dense_halves)?;
-        let g = self.assign_digest_word(region, efgh_row + 1, a_3, a_4, a_5, g.dense_halves)?;
-        let h = self.assign_digest_word(region, efgh_row + 1, a_6, a_7, a_8, h)?;
+        let (f, f_cell) = self.assign_digest_word(region, efgh_row, a_6, a_7, a_8, f.dense_halves)?;
+        let (g, g_cell) = self.assign_digest_word(region, efgh_row + 1, a_3, a_4, a_5, g.dense_halves)?;
+        let (h, h_cell) = self.assign_digest_word(region, efgh_row + 1, a_6, a_7, a_8, h)?;
 
-        Ok([
-            BlockWord(a),
-            BlockWord(b),
-            BlockWord(c),
-            BlockWord(d),
-            BlockWord(e),
-            BlockWord(f),
-            BlockWord(g),
-            BlockWord(h),
-        ])
+        Ok((
+            [
+                BlockWord(a),
+                BlockWord(b),
+                BlockWord(c),
+                BlockWord(d),
+                BlockWord(e),
+                BlockWord(f),
+                BlockWord(g),
+                BlockWord(h),
+            ],
+            [a_cell, b_cell, c_cell, d_cell, e_cell, f_cell, g_cell, h_cell],
+        ))
     }
 
     fn assign_digest_word(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         row: usize,
         lo_col: Column<Advice>,
         hi_col: Column<Advice>,
         word_col: Column<Advice>,
-        dense_halves: RoundWordDense,
-    ) -> Result<Value<u32>, Error> {
+        dense_halves: RoundWordDense<F>,
+    ) -> Result<(Value<u32>, AssignedCell<F, F>), Error> {
         dense_halves.0.copy_advice(|| "lo", region, lo_col, row)?;
         dense_halves.1.copy_advice(|| "hi", region, hi_col, row)?;
 
         let val = dense_halves.value();
-        region.assign_advice(
+        let cell = region.assign_advice(
             || "word",
             word_col,
             row,
-            || val.map(|val| Fr::from(val as u64)),
+            || val.map(|val| F::from(val as u64)),
         )?;
 
-        Ok(val)
+        Ok((val, cell))
     }
 }
@@ -1,95 +1,58 @@
-use super::super::{RoundWord, StateWord, STATE};
-use super::{compression_util::*, CompressionConfig, State};
+use super::super::{util::i2lebsp, RoundWord, SpreadVar, SpreadWord, StateWord, STATE};
+use super::{compression_util::*, CompressionConfig, RoundWordDense, State};
 
+use ff::PrimeField;
 use halo2_proofs::{
     circuit::{Region, Value},
     plonk::Error,
 };
-use halo2curves::bn256::Fr;
+use std::convert::TryInto;
 
-impl CompressionConfig {
-    #[allow(clippy::many_single_char_names)]
+impl<F: PrimeField> CompressionConfig<F> {
+    /// Initialize from a constant state, the IV or the output of compressing a public
+    /// message prefix outside the circuit. Both halves of each word are constrained to
+    /// the fixed value.
     pub fn initialize_iv(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         iv: [u32; STATE],
-    ) -> Result<State, Error> {
-        let a_7 = self.extras[3];
-
-        // Decompose E into (6, 5, 14, 7)-bit chunks
-        let e = self.decompose_e(region, RoundIdx::Init, Value::known(iv[4]))?;
-
-        // Decompose F, G
-        let f = self.decompose_f(region, InitialRound, Value::known(iv[5]))?;
-        let g = self.decompose_g(region, InitialRound, Value::known(iv[6]))?;
-
-        // Assign H
-        let h_row = get_h_row(RoundIdx::Init);
-        let h =
-            self.assign_word_halves_dense(region, h_row, a_7, h_row + 1, a_7, Value::known(iv[7]))?;
-
-        // Decompose A into (2, 11, 9, 10)-bit chunks
-        let a = self.decompose_a(region, RoundIdx::Init, Value::known(iv[0]))?;
-
-        // Decompose B, C
-        let b = self.decompose_b(region, InitialRound, Value::known(iv[1]))?;
-        let c = self.decompose_c(region, InitialRound, Value::known(iv[2]))?;
-
-        // Assign D
-        let d_row = get_d_row(RoundIdx::Init);
-        let d =
-            self.assign_word_halves_dense(region, d_row, a_7, d_row + 1, a_7, Value::known(iv[3]))?;
-
-        Ok(State::new(
-            StateWord::A(a),
-            StateWord::B(b),
-            StateWord::C(c),
-            StateWord::D(d),
-            StateWord::E(e),
-            StateWord::F(f),
-            StateWord::G(g),
-            StateWord::H(h),
-        ))
+    ) -> Result<State<F>, Error> {
+        let state = self.assign_state(region, iv.map(Value::known))?;
+        self.constrain_state_constant(region, &state, iv)?;
+        Ok(state)
     }
 
+    /// Decompose the words of a state. Their values are not constrained here, callers
+    /// bind them with [`Self::constrain_state_constant`] or copy constraints.
     #[allow(clippy::many_single_char_names)]
-    pub fn initialize_state(
+    pub fn assign_state(
         &self,
-        region: &mut Region<'_, Fr>,
-        state: State,
-    ) -> Result<State, Error> {
+        region: &mut Region<'_, F>,
+        state: [Value<u32>; STATE],
+    ) -> Result<State<F>, Error> {
         let a_7 = self.extras[3];
-        let (a, b, c, d, e, f, g, h) = match_state(state);
 
         // Decompose E into (6, 5, 14, 7)-bit chunks
-        let e = e.dense_halves.value();
-        let e = self.decompose_e(region, RoundIdx::Init, e)?;
+        let e = self.decompose_e(region, RoundIdx::Init, state[4])?;
 
         // Decompose F, G
-        let f = f.dense_halves.value();
-        let f = self.decompose_f(region, InitialRound, f)?;
-        let g = g.dense_halves.value();
-        let g = self.decompose_g(region, InitialRound, g)?;
+        let f = self.decompose_f(region, InitialRound, state[5])?;
+        let g = self.decompose_g(region, InitialRound, state[6])?;
 
         // Assign H
-        let h = h.value();
         let h_row = get_h_row(RoundIdx::Init);
-        let h = self.assign_word_halves_dense(region, h_row, a_7, h_row + 1, a_7, h)?;
+        let h = self.assign_word_halves_dense(region, h_row, a_7, h_row + 1, a_7, state[7])?;
 
         // Decompose A into (2, 11, 9, 10)-bit chunks
-        let a = a.dense_halves.value();
-        let a = self.decompose_a(region, RoundIdx::Init, a)?;
+        let a = self.decompose_a(region, RoundIdx::Init, state[0])?;
 
         // Decompose B, C
-        let b = b.dense_halves.value();
-        let b = self.decompose_b(region, InitialRound, b)?;
-        let c = c.dense_halves.value();
-        let c = self.decompose_c(region, InitialRound, c)?;
+        let b = self.decompose_b(region, InitialRound, state[1])?;
+        let c = self.decompose_c(region, InitialRound, state[2])?;
 
         // Assign D
-        let d = d.value();
         let d_row = get_d_row(RoundIdx::Init);
-        let d = self.assign_word_halves_dense(region, d_row, a_7, d_row + 1, a_7, d)?;
+        let d = self.assign_word_halves_dense(region, d_row, a_7, d_row + 1, a_7, state[3])?;
 
         Ok(State::new(
             StateWord::A(a),
@@ -103,81 +66,107 @@ impl CompressionConfig {
         ))
     }
 
-    #[allow(clippy::many_single_char_names)]
-    pub fn add_state_onto_state(
+    /// Constrain both halves of each word of `state` to the words of `words`.
+    pub fn constrain_state_constant(
         &self,
-        region: &mut Region<'_, Fr>,
-        state: State,
-        state2: State,
-    ) -> Result<State, Error> {
-
-        let (a1, b1, c1, d1, e1, f1, g1, h1) = {
-            let (a, b, c, d, e, f, g, h) = match_state(state2);
-
-            (
-                a.dense_halves.value(), // a
-                b.dense_halves.value(), // b
-                c.dense_halves.value(), // c
-                d.value(), // d
-                e.dense_halves.value(), // e
-                f.dense_halves.value(), // f
-                g.dense_halves.value(), // g
-                h.value(), // h
-            )
-        };
-
-        let a_7 = self.extras[3];
-        let (a, b, c, d, e, f, g, h) = match_state(state);
-
-        // Decompose E into (6, 5, 14, 7)-bit chunks
-        let e = e.dense_halves.value();
-        let e = self.decompose_e(region, RoundIdx::Init, e.zip(e1).map(|v| v.0.wrapping_add(v.1)))?;
-
-        // Decompose F, G
-        let f = f.dense_halves.value();
-        let f = self.decompose_f(region, InitialRound, f.zip(f1).map(|v| v.0.wrapping_add(v.1)))?;
-        let g = g.dense_halves.value();
-        let g = self.decompose_g(region, InitialRound, g.zip(g1).map(|v| v.0.wrapping_add(v.1)))?;
-
-        // Assign H
-        let h = h.value();
-        let h_row = get_h_row(RoundIdx::Init);
-        let h = self.assign_word_halves_dense(region, h_row, a_7, h_row + 1, a_7, h.zip(h1).map(|v| v.0.wrapping_add(v.1)))?;
-
-        // Decompose A into (2, 11, 9, 10)-bit chunks
-        let a = a.dense_halves.value();
-        let a = self.decompose_a(region, RoundIdx::Init, a.zip(a1).map(|v| v.0.wrapping_add(v.1)))?;
+        region: &mut Region<'_, F>,
+        state: &State<F>,
+        words: [u32; STATE],
+    ) -> Result<(), Error> {
+        for (word, value) in dense_halves(state.clone()).iter().zip(words) {
+            region.constrain_constant(word.0.cell(), F::from((value & 0xffff) as u64))?;
+            region.constrain_constant(word.1.cell(), F::from((value >> 16) as u64))?;
+        }
+        Ok(())
+    }
 
-        // Decompose B, C
-        let b = b.dense_halves.value();
-        let b = self.decompose_b(region, InitialRound, b.zip(b1).map(|v| v.0.wrapping_add(v.1)))?;
-        let c = c.dense_halves.value();
-        let c = self.decompose_c(region, InitialRound, c.zip(c1).map(|v| v.0.wrapping_add(v.1)))?;
+    /// Initialize from the output state of a previous block, copying the halves of
+    /// each word.
+    pub fn initialize_state(
+        &self,
+        region: &mut Region<'_, F>,
+        state: State<F>,
+    ) -> Result<State<F>, Error> {
+        self.initialize_dense(region, &dense_halves(state))
+    }
 
-        // Assign D
-        let d = d.value();
-        let d_row = get_d_row(RoundIdx::Init);
-        let d = self.assign_word_halves_dense(region, d_row, a_7, d_row + 1, a_7, d.zip(d1).map(|v| v.0.wrapping_add(v.1)))?;
+    /// Decompose `words` into an initialized state, copying the halves of each word.
+    pub fn initialize_dense(
+        &self,
+        region: &mut Region<'_, F>,
+        words: &[RoundWordDense<F>; STATE],
+    ) -> Result<State<F>, Error> {
+        let values = std::array::from_fn(|idx| words[idx].value());
+        let state = self.assign_state(region, values)?;
+        self.constrain_state_equal(region, &state, words)?;
+        Ok(state)
+    }
 
-        Ok(State::new(
-            StateWord::A(a),
-            StateWord::B(b),
-            StateWord::C(c),
-            StateWord::D(d),
-            StateWord::E(e),
-            StateWord::F(f),
-            StateWord::G(g),
-            StateWord::H(h),
-        ))
+    /// Constrain both halves of each word of `state` to equal those of `words`.
+    pub fn constrain_state_equal(
+        &self,
+        region: &mut Region<'_, F>,
+        state: &State<F>,
+        words: &[RoundWordDense<F>; STATE],
+    ) -> Result<(), Error> {
+        for (word, other) in dense_halves(state.clone()).iter().zip(words.iter()) {
+            region.constrain_equal(word.0.cell(), other.0.cell())?;
+            region.constrain_equal(word.1.cell(), other.1.cell())?;
+        }
+        Ok(())
     }
 
+    /// Assign the feed-forward `x + y` modulo 2^32 of each pair of words, given the
+    /// witnessed `sums`. The halves of `x` and `y` are copied in, and the halves of each
+    /// sum are range checked by the spread table.
+    pub fn assign_feed_forward(
+        &self,
+        region: &mut Region<'_, F>,
+        x: &[RoundWordDense<F>; STATE],
+        y: &[RoundWordDense<F>; STATE],
+        sums: [Value<u32>; STATE],
+    ) -> Result<[RoundWordDense<F>; STATE], Error> {
+        // Rename these here for ease of matching the gates to the specification.
+        let a_3 = self.extras[0];
+        let a_4 = self.extras[1];
+        let a_9 = self.extras[5];
+
+        let mut words = Vec::with_capacity(STATE);
+        for (idx, ((x, y), sum)) in x.iter().zip(y.iter()).zip(sums).enumerate() {
+            let row = 2 * idx;
+            self.s_feed_forward.enable(region, row)?;
+
+            x.0.copy_advice(|| "x_lo", region, a_3, row)?;
+            x.1.copy_advice(|| "x_hi", region, a_3, row + 1)?;
+            y.0.copy_advice(|| "y_lo", region, a_4, row)?;
+            y.1.copy_advice(|| "y_hi", region, a_4, row + 1)?;
+
+            let carry = x
+                .value()
+                .zip(y.value())
+                .map(|(x, y)| (x as u64 + y as u64) >> 32);
+            region.assign_advice(|| "carry", a_9, row, || carry.map(F::from))?;
+
+            let sum: Value<[bool; 32]> = sum.map(|w| i2lebsp(w.into()));
+            let lo: Value<[bool; 16]> = sum.map(|w| w[..16].try_into().unwrap());
+            let hi: Value<[bool; 16]> = sum.map(|w| w[16..].try_into().unwrap());
+            let lo = lo.map(SpreadWord::<16, 32>::new);
+            let lo = SpreadVar::with_lookup(region, &self.lookup, row, lo)?;
+            let hi = hi.map(SpreadWord::<16, 32>::new);
+            let hi = SpreadVar::with_lookup(region, &self.lookup, row + 1, hi)?;
+
+            words.push(RoundWordDense::from((lo.dense, hi.dense)));
+        }
+
+        Ok(words.try_into().unwrap())
+    }
 
     fn decompose_b(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: InitialRound,
         b_val: Value<u32>,
-    ) -> Result<RoundWord, Error> {
+    ) -> Result<RoundWord<F>, Error> {
         let row = get_decompose_b_row(round_idx);
 
         let (dense_halves, spread_halves) = self.assign_word_halves(region, row, b_val)?;
@@ -187,10 +176,10 @@ impl CompressionConfig {
 
     fn decompose_c(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: InitialRound,
         c_val: Value<u32>,
-    ) -> Result<RoundWord, Error> {
+    ) -> Result<RoundWord<F>, Error> {
         let row = get_decompose_c_row(round_idx);
 
         let (dense_halves, spread_halves) = self.assign_word_halves(region, row, c_val)?;
@@ -200,10 +189,10 @@ impl CompressionConfig {
 
     fn decompose_f(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: InitialRound,
         f_val: Value<u32>,
-    ) -> Result<RoundWord, Error> {
+    ) -> Result<RoundWord<F>, Error> {
         let row = get_decompose_f_row(round_idx);
 
         let (dense_halves, spread_halves) = self.assign_word_halves(region, row, f_val)?;
@@ -213,10 +202,10 @@ impl CompressionConfig {
 
     fn decompose_g(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         round_idx: InitialRound,
         g_val: Value<u32>,
-    ) -> Result<RoundWord, Error> {
+    ) -> Result<RoundWord<F>, Error> {
         let row = get_decompose_g_row(round_idx);
 
         let (dense_halves, spread_halves) = self.assign_word_halves(region, row, g_val)?;
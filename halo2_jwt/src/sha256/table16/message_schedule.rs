@@ -1,12 +1,13 @@
 use std::convert::TryInto;
+use std::marker::PhantomData;
 
 use super::{super::BLOCK_SIZE, AssignedBits, BlockWord, SpreadInputs, Table16Assignment, ROUNDS};
+use ff::PrimeField;
 use halo2_proofs::{
     circuit::Layouter,
     plonk::{Advice, Column, ConstraintSystem, Error, Selector},
     poly::Rotation,
 };
-use halo2curves::bn256::Fr;
 
 mod schedule_gates;
 mod schedule_util;
@@ -21,10 +22,10 @@ use schedule_util::*;
 pub use schedule_util::msg_schedule_test_input;
 
 #[derive(Clone, Debug)]
-pub(super) struct MessageWord(AssignedBits<32>);
+pub(super) struct MessageWord<F: PrimeField>(AssignedBits<F, 32>);
 
-impl std::ops::Deref for MessageWord {
-    type Target = AssignedBits<32>;
+impl<F: PrimeField> std::ops::Deref for MessageWord<F> {
+    type Target = AssignedBits<F, 32>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -32,7 +33,7 @@ impl std::ops::Deref for MessageWord {
 }
 
 #[derive(Clone, Debug)]
-pub(super) struct MessageScheduleConfig {
+pub(super) struct MessageScheduleConfig<F: PrimeField> {
     lookup: SpreadInputs,
     message_schedule: Column<Advice>,
     extras: [Column<Advice>; 6],
@@ -55,11 +56,12 @@ pub(super) struct MessageScheduleConfig {
     s_lower_sigma_0_v2: Selector,
     /// sigma_1_v2 gate for W_[14..49]
     s_lower_sigma_1_v2: Selector,
+    _marker: PhantomData<F>,
 }
 
-impl Table16Assignment for MessageScheduleConfig {}
+impl<F: PrimeField> Table16Assignment<F> for MessageScheduleConfig<F> {}
 
-impl MessageScheduleConfig {
+impl<F: PrimeField> MessageScheduleConfig<F> {
     /// Configures the message schedule.
     ///
     /// `message_schedule` is the column into which the message schedule will be placed.
@@ -71,7 +73,7 @@ impl MessageScheduleConfig {
     /// itself.
     #[allow(clippy::many_single_char_names)]
     pub(super) fn configure(
-        meta: &mut ConstraintSystem<Fr>,
+        meta: &mut ConstraintSystem<F>,
         lookup: SpreadInputs,
         message_schedule: Column<Advice>,
         extras: [Column<Advice>; 6],
@@ -298,29 +300,67 @@ impl MessageScheduleConfig {
             s_lower_sigma_1,
             s_lower_sigma_0_v2,
             s_lower_sigma_1_v2,
+            _marker: PhantomData,
         }
     }
 
     #[allow(clippy::type_complexity)]
     pub(super) fn process(
         &self,
-        layouter: &mut impl Layouter<Fr>,
+        layouter: &mut impl Layouter<F>,
         input: [BlockWord; BLOCK_SIZE],
     ) -> Result<
         (
-            [MessageWord; ROUNDS],
-            [(AssignedBits<16>, AssignedBits<16>); ROUNDS],
+            [MessageWord<F>; ROUNDS],
+            [(AssignedBits<F, 16>, AssignedBits<F, 16>); ROUNDS],
         ),
         Error,
     > {
-        let mut w = Vec::<MessageWord>::with_capacity(ROUNDS);
-        let mut w_halves = Vec::<(AssignedBits<16>, AssignedBits<16>)>::with_capacity(ROUNDS);
+        self.process_inner(layouter, input, None)
+    }
+
+    /// Processes a message block whose words have already been assigned, constraining
+    /// W_[0..16] to equal them.
+    #[allow(clippy::type_complexity)]
+    pub(super) fn process_assigned(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &[AssignedBits<F, 32>; BLOCK_SIZE],
+    ) -> Result<
+        (
+            [MessageWord<F>; ROUNDS],
+            [(AssignedBits<F, 16>, AssignedBits<F, 16>); ROUNDS],
+        ),
+        Error,
+    > {
+        let words = input
+            .iter()
+            .map(|word| BlockWord(word.value_u32()))
+            .collect::<Vec<_>>();
+        self.process_inner(layouter, words.try_into().unwrap(), Some(input))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn process_inner(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: [BlockWord; BLOCK_SIZE],
+        assigned: Option<&[AssignedBits<F, 32>; BLOCK_SIZE]>,
+    ) -> Result<
+        (
+            [MessageWord<F>; ROUNDS],
+            [(AssignedBits<F, 16>, AssignedBits<F, 16>); ROUNDS],
+        ),
+        Error,
+    > {
+        let mut w = Vec::<MessageWord<F>>::with_capacity(ROUNDS);
+        let mut w_halves = Vec::<(AssignedBits<F, 16>, AssignedBits<F, 16>)>::with_capacity(ROUNDS);
 
         layouter.assign_region(
             || "process message block",
             |mut region| {
-                w = Vec::<MessageWord>::with_capacity(ROUNDS);
-                w_halves = Vec::<(AssignedBits<16>, AssignedBits<16>)>::with_capacity(ROUNDS);
+                w = Vec::<MessageWord<F>>::with_capacity(ROUNDS);
+                w_halves = Vec::<(AssignedBits<F, 16>, AssignedBits<F, 16>)>::with_capacity(ROUNDS);
 
                 // Assign all fixed columns
                 for index in 1..14 {
@@ -359,6 +399,9 @@ impl MessageScheduleConfig {
                 // Assign W[0..16]
                 for (i, word) in input.iter().enumerate() {
                     let (word, halves) = self.assign_word_and_halves(&mut region, word.0, i)?;
+                    if let Some(assigned) = assigned {
+                        region.constrain_equal(word.cell(), assigned[i].cell())?;
+                    }
                     w.push(MessageWord(word));
                     w_halves.push(halves);
                 }
@@ -409,7 +452,7 @@ mod tests {
         struct MyCircuit {}
 
         impl Circuit<Fr> for MyCircuit {
-            type Config = Table16Config;
+            type Config = Table16Config<Fr>;
             type FloorPlanner = SimpleFloorPlanner;
             #[cfg(feature = "circuit-params")]
             type Params = ();
@@ -1,10 +1,10 @@
 use super::super::AssignedBits;
 use super::MessageScheduleConfig;
+use ff::PrimeField;
 use halo2_proofs::{
     circuit::{Region, Value},
     plonk::Error,
 };
-use halo2curves::bn256::Fr;
 
 #[cfg(test)]
 use super::super::{super::BLOCK_SIZE, BlockWord, ROUNDS};
@@ -148,14 +148,14 @@ pub const MSG_SCHEDULE_TEST_OUTPUT: [u32; ROUNDS] = [
     0b00010010101100011110110111101011,
 ];
 
-impl MessageScheduleConfig {
+impl<F: PrimeField> MessageScheduleConfig<F> {
     // Assign a word and its hi and lo halves
     pub fn assign_word_and_halves(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         word: Value<u32>,
         word_idx: usize,
-    ) -> Result<(AssignedBits<32>, (AssignedBits<16>, AssignedBits<16>)), Error> {
+    ) -> Result<(AssignedBits<F, 32>, (AssignedBits<F, 16>, AssignedBits<F, 16>)), Error> {
         // Rename these here for ease of matching the gates to the specification.
         let a_3 = self.extras[0];
         let a_4 = self.extras[1];
@@ -164,14 +164,14 @@ impl MessageScheduleConfig {
 
         let w_lo = {
             let w_lo_val = word.map(|word| word as u16);
-            AssignedBits::<16>::assign(region, || format!("W_{}_lo", word_idx), a_3, row, w_lo_val)?
+            AssignedBits::<F, 16>::assign(region, || format!("W_{}_lo", word_idx), a_3, row, w_lo_val)?
         };
         let w_hi = {
             let w_hi_val = word.map(|word| (word >> 16) as u16);
-            AssignedBits::<16>::assign(region, || format!("W_{}_hi", word_idx), a_4, row, w_hi_val)?
+            AssignedBits::<F, 16>::assign(region, || format!("W_{}_hi", word_idx), a_4, row, w_hi_val)?
         };
 
-        let word = AssignedBits::<32>::assign(
+        let word = AssignedBits::<F, 32>::assign(
             region,
             || format!("W_{}", word_idx),
             self.message_schedule,
@@ -2,23 +2,27 @@ use std::convert::TryInto;
 use std::marker::PhantomData;
 
 use super::Sha256Instructions;
+use ff::{Field, PrimeField};
 use halo2_proofs::{
     circuit::{AssignedCell, Chip, Layouter, Region, Value},
     plonk::{Advice, Any, Assigned, Column, ConstraintSystem, Error},
 };
-use halo2curves::bn256::Fr;
 
 mod compression;
 mod gates;
 mod message_schedule;
 mod spread_table;
 mod util;
+mod word_packing;
 
 use compression::*;
 use gates::*;
 use message_schedule::*;
 use spread_table::*;
 use util::*;
+use word_packing::*;
+
+pub use spread_table::SpreadTableKind;
 
 const ROUNDS: usize = 64;
 const STATE: usize = 8;
@@ -35,7 +39,8 @@ pub(crate) const ROUND_CONSTANTS: [u32; ROUNDS] = [
     0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
 ];
 
-const IV: [u32; STATE] = [
+/// The initial hash value of SHA-256, the state [`super::sha256_midstate`] starts from.
+pub const IV: [u32; STATE] = [
     0x6a09_e667,
     0xbb67_ae85,
     0x3c6e_f372,
@@ -81,10 +86,10 @@ impl<const LEN: usize> From<&Bits<LEN>> for [bool; LEN] {
     }
 }
 
-impl<const LEN: usize> From<&Bits<LEN>> for Assigned<Fr> {
-    fn from(bits: &Bits<LEN>) -> Assigned<Fr> {
+impl<F: PrimeField, const LEN: usize> From<&Bits<LEN>> for Assigned<F> {
+    fn from(bits: &Bits<LEN>) -> Assigned<F> {
         assert!(LEN <= 64);
-        Fr::from(lebs2ip(&bits.0)).into()
+        F::from(lebs2ip(&bits.0)).into()
     }
 }
 
@@ -113,19 +118,19 @@ impl From<u32> for Bits<32> {
 }
 
 #[derive(Clone, Debug)]
-pub struct AssignedBits<const LEN: usize>(AssignedCell<Bits<LEN>, Fr>);
+pub struct AssignedBits<F: Field, const LEN: usize>(AssignedCell<Bits<LEN>, F>);
 
-impl<const LEN: usize> std::ops::Deref for AssignedBits<LEN> {
-    type Target = AssignedCell<Bits<LEN>, Fr>;
+impl<F: Field, const LEN: usize> std::ops::Deref for AssignedBits<F, LEN> {
+    type Target = AssignedCell<Bits<LEN>, F>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl<const LEN: usize> AssignedBits<LEN> {
+impl<F: PrimeField, const LEN: usize> AssignedBits<F, LEN> {
     fn assign_bits<A, AR, T: TryInto<[bool; LEN]> + std::fmt::Debug + Clone>(
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         annotation: A,
         column: impl Into<Column<Any>>,
         offset: usize,
@@ -157,13 +162,13 @@ impl<const LEN: usize> AssignedBits<LEN> {
     }
 }
 
-impl AssignedBits<16> {
+impl<F: PrimeField> AssignedBits<F, 16> {
     fn value_u16(&self) -> Value<u16> {
         self.value().map(|v| v.into())
     }
 
     fn assign<A, AR>(
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         annotation: A,
         column: impl Into<Column<Any>>,
         offset: usize,
@@ -192,13 +197,13 @@ impl AssignedBits<16> {
     }
 }
 
-impl AssignedBits<32> {
+impl<F: PrimeField> AssignedBits<F, 32> {
     fn value_u32(&self) -> Value<u32> {
         self.value().map(|v| v.into())
     }
 
     fn assign<A, AR>(
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         annotation: A,
         column: impl Into<Column<Any>>,
         offset: usize,
@@ -229,21 +234,23 @@ impl AssignedBits<32> {
 
 /// Configuration for a [`Table16Chip`].
 #[derive(Clone, Debug)]
-pub struct Table16Config {
+pub struct Table16Config<F: PrimeField> {
     lookup: SpreadTableConfig,
-    message_schedule: MessageScheduleConfig,
-    compression: CompressionConfig,
+    message_schedule: MessageScheduleConfig<F>,
+    compression: CompressionConfig<F>,
+    word_packing: WordPackingConfig<F>,
 }
 
-/// A chip that implements SHA-256 with a maximum lookup table size of $2^16$.
+/// A chip that implements SHA-256 with a maximum lookup table size of $2^16$, or of
+/// 1773 rows with a [`SpreadTableKind::Split`] table.
 #[derive(Clone, Debug)]
-pub struct Table16Chip {
-    config: Table16Config,
-    _marker: PhantomData<Fr>,
+pub struct Table16Chip<F: PrimeField> {
+    config: Table16Config<F>,
+    _marker: PhantomData<F>,
 }
 
-impl Chip<Fr> for Table16Chip {
-    type Config = Table16Config;
+impl<F: PrimeField> Chip<F> for Table16Chip<F> {
+    type Config = Table16Config<F>;
     type Loaded = ();
 
     fn config(&self) -> &Self::Config {
@@ -255,19 +262,28 @@ impl Chip<Fr> for Table16Chip {
     }
 }
 
-impl Table16Chip {
+impl<F: PrimeField> Table16Chip<F> {
     /// Reconstructs this chip from the given config.
-    pub fn construct(config: <Self as Chip<Fr>>::Config) -> Self {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
         Self {
             config,
             _marker: PhantomData,
         }
     }
 
-    /// Configures a circuit to include this chip.
+    /// Configures a circuit to include this chip, with the full spread table.
     pub fn configure(
-        meta: &mut ConstraintSystem<Fr>,
-    ) -> <Self as Chip<Fr>>::Config {
+        meta: &mut ConstraintSystem<F>,
+    ) -> <Self as Chip<F>>::Config {
+        Self::configure_with_table(meta, SpreadTableKind::Full)
+    }
+
+    /// Configures a circuit to include this chip, with the given kind of spread table.
+    /// The kind only changes how words are range checked, so digests are the same.
+    pub fn configure_with_table(
+        meta: &mut ConstraintSystem<F>,
+        table: SpreadTableKind,
+    ) -> <Self as Chip<F>>::Config {
         // Columns required by this chip:
         let message_schedule = meta.advice_column();
         let extras = [
@@ -284,7 +300,7 @@ impl Table16Chip {
         let input_dense = meta.advice_column();
         let input_spread = meta.advice_column();
 
-        let lookup = SpreadTableChip::configure(meta, input_tag, input_dense, input_spread);
+        let lookup = SpreadTableChip::configure(meta, input_tag, input_dense, input_spread, table);
         let lookup_inputs = lookup.input.clone();
 
         // Rename these here for ease of matching the gates to the specification.
@@ -304,9 +320,16 @@ impl Table16Chip {
             meta.enable_equality(*column);
         }
 
+        // The IV and public midstates are constrained to fixed values.
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
         let compression =
             CompressionConfig::configure(meta, lookup_inputs.clone(), message_schedule, extras);
 
+        let word_packing =
+            WordPackingConfig::configure(meta, lookup_inputs.clone(), message_schedule);
+
         let message_schedule =
             MessageScheduleConfig::configure(meta, lookup_inputs, message_schedule, extras);
 
@@ -314,32 +337,33 @@ impl Table16Chip {
             lookup,
             message_schedule,
             compression,
+            word_packing,
         }
     }
 
     /// Loads the lookup table required by this chip into the circuit.
     pub fn load(
-        config: Table16Config,
-        layouter: &mut impl Layouter<Fr>,
+        config: Table16Config<F>,
+        layouter: &mut impl Layouter<F>,
     ) -> Result<(), Error> {
         SpreadTableChip::load(config.lookup, layouter)
     }
 }
 
-impl Sha256Instructions<Fr> for Table16Chip {
-    type State = State;
+impl<F: PrimeField> Sha256Instructions<F> for Table16Chip<F> {
+    type State = State<F>;
     type BlockWord = BlockWord;
 
     fn initialization_vector(
         &self,
-        layouter: &mut impl Layouter<Fr>,
-    ) -> Result<State, Error> {
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<State<F>, Error> {
         self.config().compression.initialize_with_iv(layouter, IV)
     }
 
     fn initialization(
         &self,
-        layouter: &mut impl Layouter<Fr>,
+        layouter: &mut impl Layouter<F>,
         init_state: &Self::State,
     ) -> Result<Self::State, Error> {
         self.config()
@@ -347,11 +371,21 @@ impl Sha256Instructions<Fr> for Table16Chip {
             .initialize_with_state(layouter, init_state.clone())
     }
 
+    fn initialization_from_public_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[u32; super::DIGEST_SIZE],
+    ) -> Result<Self::State, Error> {
+        self.config()
+            .compression
+            .initialize_with_midstate(layouter, *state)
+    }
+
     // Given an initialized state and an input message block, compress the
     // message block and return the final state.
     fn compress(
         &self,
-        layouter: &mut impl Layouter<Fr>,
+        layouter: &mut impl Layouter<F>,
         initialized_state: &Self::State,
         input: [Self::BlockWord; super::BLOCK_SIZE],
     ) -> Result<Self::State, Error> {
@@ -365,25 +399,90 @@ impl Sha256Instructions<Fr> for Table16Chip {
         config.compression.add_state_to_state(layouter, state, init_state)
     }
 
+    fn compress_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: &[AssignedCell<F, F>; super::BLOCK_SIZE_BYTES],
+    ) -> Result<Self::State, Error> {
+        let config = self.config();
+        let words = config.word_packing.pack(layouter, input)?;
+        let (_, w_halves) = config.message_schedule.process_assigned(layouter, &words)?;
+
+        let init_state = initialized_state.clone();
+        let state = config
+            .compression
+            .compress(layouter, initialized_state.clone(), w_halves)?;
+        config.compression.add_state_to_state(layouter, state, init_state)
+    }
+
     fn digest(
         &self,
-        layouter: &mut impl Layouter<Fr>,
+        layouter: &mut impl Layouter<F>,
         state: &Self::State,
     ) -> Result<[Self::BlockWord; super::DIGEST_SIZE], Error> {
         // Copy the dense forms of the state variable chunks down to this gate.
         // Reconstruct the 32-bit dense words.
-        self.config().compression.digest(layouter, state.clone())
+        let (digest, _) = self.config().compression.digest(layouter, state.clone())?;
+        Ok(digest)
+    }
+
+    fn assigned_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[AssignedCell<F, F>; super::DIGEST_SIZE], Error> {
+        let (_, words) = self.config().compression.digest(layouter, state.clone())?;
+        Ok(words)
+    }
+}
+
+#[cfg(test)]
+impl<F: PrimeField> Table16Chip<F> {
+    /// Compresses a block like [`Sha256Instructions::compress`], but witnesses the word
+    /// `idx` of the feed-forward sum off by one.
+    pub(crate) fn compress_with_wrong_sum(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &State<F>,
+        input: [BlockWord; super::BLOCK_SIZE],
+        idx: usize,
+    ) -> Result<State<F>, Error> {
+        let config = self.config();
+        let (_, w_halves) = config.message_schedule.process(layouter, input)?;
+        let state = config
+            .compression
+            .compress(layouter, initialized_state.clone(), w_halves)?;
+
+        let x = dense_halves(state);
+        let y = dense_halves(initialized_state.clone());
+        let mut sums: [Value<u32>; STATE] = std::array::from_fn(|idx| {
+            x[idx]
+                .value()
+                .zip(y[idx].value())
+                .map(|(x, y)| x.wrapping_add(y))
+        });
+        sums[idx] = sums[idx].map(|sum| sum.wrapping_add(1));
+
+        let sums = layouter.assign_region(
+            || "feed_forward",
+            |mut region| config.compression.assign_feed_forward(&mut region, &x, &y, sums),
+        )?;
+        layouter.assign_region(
+            || "add_iv_to_state",
+            |mut region| config.compression.initialize_dense(&mut region, &sums),
+        )
     }
 }
 
 /// Common assignment patterns used by Table16 regions.
-trait Table16Assignment {
+trait Table16Assignment<F: PrimeField> {
     /// Assign cells for general spread computation used in sigma, ch, ch_neg, maj gates
     #[allow(clippy::too_many_arguments)]
     #[allow(clippy::type_complexity)]
     fn assign_spread_outputs(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         lookup: &SpreadInputs,
         a_3: Column<Advice>,
         row: usize,
@@ -393,8 +492,8 @@ trait Table16Assignment {
         r_1_odd: Value<[bool; 16]>,
     ) -> Result<
         (
-            (AssignedBits<16>, AssignedBits<16>),
-            (AssignedBits<16>, AssignedBits<16>),
+            (AssignedBits<F, 16>, AssignedBits<F, 16>),
+            (AssignedBits<F, 16>, AssignedBits<F, 16>),
         ),
         Error,
     > {
@@ -435,7 +534,7 @@ trait Table16Assignment {
     #[allow(clippy::too_many_arguments)]
     fn assign_sigma_outputs(
         &self,
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         lookup: &SpreadInputs,
         a_3: Column<Advice>,
         row: usize,
@@ -443,7 +542,7 @@ trait Table16Assignment {
         r_0_odd: Value<[bool; 16]>,
         r_1_even: Value<[bool; 16]>,
         r_1_odd: Value<[bool; 16]>,
-    ) -> Result<(AssignedBits<16>, AssignedBits<16>), Error> {
+    ) -> Result<(AssignedBits<F, 16>, AssignedBits<F, 16>), Error> {
         let (even, _odd) = self.assign_spread_outputs(
             region, lookup, a_3, row, r_0_even, r_0_odd, r_1_even, r_1_odd,
         )?;
@@ -461,7 +560,7 @@ mod tests {
         circuit::{Layouter, SimpleFloorPlanner},
         plonk::{Circuit, ConstraintSystem, Error},
     };
-    use halo2curves::pasta::pallas;
+    use halo2curves::bn256::Fr;
 
     #[test]
     fn print_sha256_circuit() {
@@ -469,7 +568,7 @@ mod tests {
         struct MyCircuit {}
 
         impl Circuit<Fr> for MyCircuit {
-            type Config = Table16Config;
+            type Config = Table16Config<Fr>;
             type FloorPlanner = SimpleFloorPlanner;
             #[cfg(feature = "circuit-params")]
             type Params = ();
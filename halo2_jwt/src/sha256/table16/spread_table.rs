@@ -6,7 +6,6 @@ use halo2_proofs::{
     plonk::{Advice, Column, ConstraintSystem, Error, TableColumn},
     poly::Rotation,
 };
-use halo2curves::bn256::Fr;
 use std::convert::TryInto;
 use std::marker::PhantomData;
 
@@ -16,6 +15,24 @@ const BITS_11: usize = 1 << 11;
 const BITS_13: usize = 1 << 13;
 const BITS_14: usize = 1 << 14;
 
+/// The bit length bounded by each tag.
+const TAG_BITS: [usize; 6] = [7, 10, 11, 13, 14, 16];
+/// Tags of the high bytes in a split table follow the tags of the low bytes.
+const HIGH_TAG_OFFSET: usize = TAG_BITS.len();
+
+/// How 16-bit words are looked up in the spread table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpreadTableKind {
+    /// Every 16-bit word and its tag is a row of the table, which takes `2^16` rows, so
+    /// a circuit needs `k >= 17`.
+    #[default]
+    Full,
+    /// A 16-bit word is split into bytes, each looked up with the tag in a table of 1773
+    /// rows, so a circuit fits in `k >= 11`. Costs two advice columns and a second
+    /// lookup argument.
+    Split,
+}
+
 /// An input word into a lookup, containing (tag, dense, spread)
 #[derive(Copy, Clone, Debug)]
 pub(super) struct SpreadWord<const DENSE: usize, const SPREAD: usize> {
@@ -68,15 +85,15 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadWord<DENSE, SPREAD> {
 
 /// A variable stored in advice columns corresponding to a row of [`SpreadTableConfig`].
 #[derive(Clone, Debug)]
-pub(super) struct SpreadVar<const DENSE: usize, const SPREAD: usize> {
+pub(super) struct SpreadVar<F: PrimeField, const DENSE: usize, const SPREAD: usize> {
     pub tag: Value<u8>,
-    pub dense: AssignedBits<DENSE>,
-    pub spread: AssignedBits<SPREAD>,
+    pub dense: AssignedBits<F, DENSE>,
+    pub spread: AssignedBits<F, SPREAD>,
 }
 
-impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
+impl<F: PrimeField, const DENSE: usize, const SPREAD: usize> SpreadVar<F, DENSE, SPREAD> {
     pub(super) fn with_lookup(
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         cols: &SpreadInputs,
         row: usize,
         word: Value<SpreadWord<DENSE, SPREAD>>,
@@ -89,20 +106,22 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
             || "tag",
             cols.tag,
             row,
-            || tag.map(|tag| Fr::from(tag as u64)),
+            || tag.map(|tag| F::from(tag as u64)),
         )?;
 
         let dense =
-            AssignedBits::<DENSE>::assign_bits(region, || "dense", cols.dense, row, dense_val)?;
+            AssignedBits::<F, DENSE>::assign_bits(region, || "dense", cols.dense, row, dense_val)?;
 
         let spread =
-            AssignedBits::<SPREAD>::assign_bits(region, || "spread", cols.spread, row, spread_val)?;
+            AssignedBits::<F, SPREAD>::assign_bits(region, || "spread", cols.spread, row, spread_val)?;
+
+        cols.assign_high(region, row, dense_val.map(|dense| lebs2ip(&dense) as u16))?;
 
         Ok(SpreadVar { tag, dense, spread })
     }
 
     pub(super) fn without_lookup(
-        region: &mut Region<'_, Fr>,
+        region: &mut Region<'_, F>,
         dense_col: Column<Advice>,
         dense_row: usize,
         spread_col: Column<Advice>,
@@ -113,7 +132,7 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
         let dense_val = word.map(|word| word.dense);
         let spread_val = word.map(|word| word.spread);
 
-        let dense = AssignedBits::<DENSE>::assign_bits(
+        let dense = AssignedBits::<F, DENSE>::assign_bits(
             region,
             || "dense",
             dense_col,
@@ -121,7 +140,7 @@ impl<const DENSE: usize, const SPREAD: usize> SpreadVar<DENSE, SPREAD> {
             dense_val,
         )?;
 
-        let spread = AssignedBits::<SPREAD>::assign_bits(
+        let spread = AssignedBits::<F, SPREAD>::assign_bits(
             region,
             || "spread",
             spread_col,
@@ -138,6 +157,36 @@ pub(super) struct SpreadInputs {
     pub(super) tag: Column<Advice>,
     pub(super) dense: Column<Advice>,
     pub(super) spread: Column<Advice>,
+    /// The high byte of `dense` and its spread, when the table is split.
+    pub(super) high: Option<(Column<Advice>, Column<Advice>)>,
+}
+
+impl SpreadInputs {
+    /// Assigns the high byte of the 16-bit `dense` looked up at `row`, if the table is
+    /// split. Must be called for every row of the lookup columns.
+    pub(super) fn assign_high<F: PrimeField>(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        dense: Value<u16>,
+    ) -> Result<(), Error> {
+        if let Some((dense_hi, spread_hi)) = self.high {
+            let hi = dense.map(|dense| (dense >> 8) as u64);
+            region.assign_advice(|| "dense_hi", dense_hi, row, || hi.map(F::from))?;
+            region.assign_advice(
+                || "spread_hi",
+                spread_hi,
+                row,
+                || hi.map(|hi| F::from(spread_u64(hi))),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The spread of a value of at most 16 bits.
+fn spread_u64(dense: u64) -> u64 {
+    lebs2ip(&spread_bits::<16, 32>(i2lebsp::<16>(dense)))
 }
 
 #[derive(Clone, Debug)]
@@ -151,6 +200,7 @@ pub(super) struct SpreadTable {
 pub(super) struct SpreadTableConfig {
     pub input: SpreadInputs,
     pub table: SpreadTable,
+    pub kind: SpreadTableKind,
 }
 
 #[derive(Clone, Debug)]
@@ -178,34 +228,78 @@ impl<F: PrimeField> SpreadTableChip<F> {
         input_tag: Column<Advice>,
         input_dense: Column<Advice>,
         input_spread: Column<Advice>,
+        kind: SpreadTableKind,
     ) -> <Self as Chip<F>>::Config {
         let table_tag = meta.lookup_table_column();
         let table_dense = meta.lookup_table_column();
         let table_spread = meta.lookup_table_column();
 
-        meta.lookup("lookup", |meta| {
-            let tag_cur = meta.query_advice(input_tag, Rotation::cur());
-            let dense_cur = meta.query_advice(input_dense, Rotation::cur());
-            let spread_cur = meta.query_advice(input_spread, Rotation::cur());
-
-            vec![
-                (tag_cur, table_tag),
-                (dense_cur, table_dense),
-                (spread_cur, table_spread),
-            ]
-        });
+        let high = match kind {
+            SpreadTableKind::Full => {
+                meta.lookup("lookup", |meta| {
+                    let tag_cur = meta.query_advice(input_tag, Rotation::cur());
+                    let dense_cur = meta.query_advice(input_dense, Rotation::cur());
+                    let spread_cur = meta.query_advice(input_spread, Rotation::cur());
+
+                    vec![
+                        (tag_cur, table_tag),
+                        (dense_cur, table_dense),
+                        (spread_cur, table_spread),
+                    ]
+                });
+
+                None
+            }
+            SpreadTableKind::Split => {
+                let input_dense_hi = meta.advice_column();
+                let input_spread_hi = meta.advice_column();
+
+                // dense = lo + 2^8 hi and spread = spread(lo) + 2^16 spread(hi). The low
+                // byte is bounded by the tag when the tag bounds the word to 7 bits, the
+                // high byte by the remaining bits of the tag, so the word is bounded by
+                // the tag as in the full table.
+                meta.lookup("lookup low", |meta| {
+                    let tag_cur = meta.query_advice(input_tag, Rotation::cur());
+                    let dense_cur = meta.query_advice(input_dense, Rotation::cur());
+                    let spread_cur = meta.query_advice(input_spread, Rotation::cur());
+                    let dense_hi = meta.query_advice(input_dense_hi, Rotation::cur());
+                    let spread_hi = meta.query_advice(input_spread_hi, Rotation::cur());
+
+                    vec![
+                        (tag_cur, table_tag),
+                        (dense_cur - dense_hi * F::from(1 << 8), table_dense),
+                        (spread_cur - spread_hi * F::from(1 << 16), table_spread),
+                    ]
+                });
+                meta.lookup("lookup high", |meta| {
+                    let tag_cur = meta.query_advice(input_tag, Rotation::cur());
+                    let dense_hi = meta.query_advice(input_dense_hi, Rotation::cur());
+                    let spread_hi = meta.query_advice(input_spread_hi, Rotation::cur());
+
+                    vec![
+                        (tag_cur + F::from(HIGH_TAG_OFFSET as u64), table_tag),
+                        (dense_hi, table_dense),
+                        (spread_hi, table_spread),
+                    ]
+                });
+
+                Some((input_dense_hi, input_spread_hi))
+            }
+        };
 
         SpreadTableConfig {
             input: SpreadInputs {
                 tag: input_tag,
                 dense: input_dense,
                 spread: input_spread,
+                high,
             },
             table: SpreadTable {
                 tag: table_tag,
                 dense: table_dense,
                 spread: table_spread,
             },
+            kind,
         }
     }
 
@@ -217,9 +311,14 @@ impl<F: PrimeField> SpreadTableChip<F> {
             || "spread table",
             |mut table| {
                 // We generate the row values lazily (we only need them during keygen).
-                let mut rows = SpreadTableConfig::generate::<F>();
+                let (mut rows, len): (Box<dyn Iterator<Item = (F, F, F)>>, _) = match config.kind {
+                    SpreadTableKind::Full => (Box::new(SpreadTableConfig::generate::<F>()), 1 << 16),
+                    SpreadTableKind::Split => {
+                        (Box::new(SpreadTableConfig::generate_split::<F>()), SpreadTableConfig::split_len())
+                    }
+                };
 
-                for index in 0..(1 << 16) {
+                for index in 0..len {
                     let mut row = None;
                     table.assign_cell(
                         || "tag",
@@ -278,13 +377,36 @@ impl SpreadTableConfig {
             Some(res)
         })
     }
+
+    /// The rows of a split table: for every tag, the low bytes it allows then the high
+    /// bytes it allows, tagged after the low bytes.
+    fn generate_split<F: PrimeField>() -> impl Iterator<Item = (F, F, F)> {
+        let low = TAG_BITS
+            .iter()
+            .enumerate()
+            .flat_map(|(tag, bits)| (0..1u64 << (*bits).min(8)).map(move |dense| (tag, dense)));
+        let high = TAG_BITS.iter().enumerate().flat_map(|(tag, bits)| {
+            (0..1u64 << bits.saturating_sub(8)).map(move |dense| (tag + HIGH_TAG_OFFSET, dense))
+        });
+
+        low.chain(high)
+            .map(|(tag, dense)| (F::from(tag as u64), F::from(dense), F::from(spread_u64(dense))))
+    }
+
+    fn split_len() -> usize {
+        TAG_BITS
+            .iter()
+            .map(|bits| (1 << (*bits).min(8)) + (1 << bits.saturating_sub(8)))
+            .sum()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{get_tag, SpreadTableChip, SpreadTableConfig};
+    use super::{get_tag, SpreadTableChip, SpreadTableConfig, SpreadTableKind};
     use ff::PrimeField;
     use rand::Rng;
+    use std::convert::TryInto;
 
     use halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner, Value},
@@ -295,13 +417,25 @@ mod tests {
 
     #[test]
     fn lookup_table() {
+        lookup_table_of::<false>(17);
+    }
+
+    #[test]
+    fn split_lookup_table() {
+        assert_eq!(SpreadTableConfig::split_len(), 1773);
+        assert_eq!(SpreadTableConfig::generate_split::<Fp>().count(), 1773);
+
+        lookup_table_of::<true>(11);
+    }
+
+    fn lookup_table_of<const SPLIT: bool>(k: u32) {
         /// This represents an advice column at a certain row in the ConstraintSystem
         #[derive(Copy, Clone, Debug)]
         pub struct Variable(Column<Advice>, usize);
 
-        struct MyCircuit {}
+        struct MyCircuit<const SPLIT: bool> {}
 
-        impl<F: PrimeField> Circuit<F> for MyCircuit {
+        impl<F: PrimeField, const SPLIT: bool> Circuit<F> for MyCircuit<SPLIT> {
             type Config = SpreadTableConfig;
             type FloorPlanner = SimpleFloorPlanner;
             #[cfg(feature = "circuit-params")]
@@ -316,7 +450,8 @@ mod tests {
                 let input_dense = meta.advice_column();
                 let input_spread = meta.advice_column();
 
-                SpreadTableChip::configure(meta, input_tag, input_dense, input_spread)
+                let kind = if SPLIT { SpreadTableKind::Split } else { SpreadTableKind::Full };
+                SpreadTableChip::configure(meta, input_tag, input_dense, input_spread, kind)
             }
 
             fn synthesize(
@@ -349,6 +484,8 @@ mod tests {
                                 row,
                                 || Value::known(spread),
                             )?;
+                            let dense = u16::from_le_bytes(dense.to_repr().as_ref()[..2].try_into().unwrap());
+                            config.input.assign_high(&mut gate, row, Value::known(dense))?;
                             row += 1;
                             Ok(())
                         };
@@ -437,9 +574,9 @@ mod tests {
             }
         }
 
-        let circuit: MyCircuit = MyCircuit {};
+        let circuit: MyCircuit<SPLIT> = MyCircuit {};
 
-        let prover = match MockProver::<Fp>::run(17, &circuit, vec![]) {
+        let prover = match MockProver::<Fp>::run(k, &circuit, vec![]) {
             Ok(prover) => prover,
             Err(e) => panic!("{:?}", e),
         };
@@ -0,0 +1,246 @@
+use std::convert::TryInto;
+use std::marker::PhantomData;
+
+use super::{
+    super::{BLOCK_SIZE, BLOCK_SIZE_BYTES},
+    util::i2lebsp,
+    AssignedBits, SpreadInputs, SpreadVar, SpreadWord,
+};
+use ff::PrimeField;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+/// Each byte occupies two rows of the lookup columns: the byte itself, and the byte
+/// shifted left by 8 bits. Both must be in the 16-bit spread table, so the byte is
+/// range checked to 8 bits.
+const ROWS_PER_BYTE: usize = 2;
+const ROWS_PER_WORD: usize = 4 * ROWS_PER_BYTE;
+
+/// Packs assigned bytes into the 32-bit words of a message block.
+#[derive(Clone, Debug)]
+pub(super) struct WordPackingConfig<F: PrimeField> {
+    lookup: SpreadInputs,
+    message_schedule: Column<Advice>,
+    s_pack: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> WordPackingConfig<F> {
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadInputs,
+        message_schedule: Column<Advice>,
+    ) -> Self {
+        let s_pack = meta.selector();
+
+        // Rename these here for ease of matching the gates to the specification.
+        let a_1 = lookup.dense;
+        let a_5 = message_schedule;
+
+        // s_pack: big-endian bytes b_0..b_3 at rows 0, 2, 4, 6 compose the word at row 0.
+        // Each byte is followed by itself shifted left by 8 bits.
+        meta.create_gate("s_pack", |meta| {
+            let s_pack = meta.query_selector(s_pack);
+            let word = meta.query_advice(a_5, Rotation::cur());
+
+            let mut constraints = Vec::with_capacity(5);
+            let mut composed = Expression::Constant(F::ZERO);
+            for i in 0..4 {
+                let byte = meta.query_advice(a_1, Rotation((i * ROWS_PER_BYTE) as i32));
+                let shifted = meta.query_advice(a_1, Rotation((i * ROWS_PER_BYTE + 1) as i32));
+
+                constraints.push(s_pack.clone() * (shifted - byte.clone() * F::from(1u64 << 8)));
+                composed = composed * F::from(1u64 << 8) + byte;
+            }
+            constraints.push(s_pack * (composed - word));
+
+            constraints
+        });
+
+        WordPackingConfig {
+            lookup,
+            message_schedule,
+            s_pack,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Copies a block of big-endian bytes into the lookup columns, range checks them,
+    /// and returns the packed message words.
+    pub(super) fn pack(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &[AssignedCell<F, F>; BLOCK_SIZE_BYTES],
+    ) -> Result<[AssignedBits<F, 32>; BLOCK_SIZE], Error> {
+        layouter.assign_region(
+            || "pack message block",
+            |mut region| {
+                let mut words = Vec::with_capacity(BLOCK_SIZE);
+                for (word_idx, bytes) in input.chunks_exact(4).enumerate() {
+                    let row = word_idx * ROWS_PER_WORD;
+                    self.s_pack.enable(&mut region, row)?;
+
+                    let mut word = Value::known(F::ZERO);
+                    for (i, byte) in bytes.iter().enumerate() {
+                        self.assign_byte(&mut region, row + i * ROWS_PER_BYTE, byte)?;
+                        word = word
+                            .zip(byte.value())
+                            .map(|(word, byte)| word * F::from(1u64 << 8) + byte);
+                    }
+                    let word = word.map(|word| {
+                        u32::from_le_bytes(word.to_repr().as_ref()[..4].try_into().unwrap())
+                    });
+
+                    words.push(AssignedBits::<F, 32>::assign(
+                        &mut region,
+                        || format!("W_{}", word_idx),
+                        self.message_schedule,
+                        row,
+                        word,
+                    )?);
+                }
+                Ok(words.try_into().unwrap())
+            },
+        )
+    }
+
+    fn assign_byte(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        byte: &AssignedCell<F, F>,
+    ) -> Result<(), Error> {
+        // A non-byte value is truncated here, so its copied dense value will not match
+        // the assigned tag and spread, and the lookup fails.
+        let value = byte.value().map(|byte| byte.to_repr().as_ref()[0]);
+
+        let spread_word = value.map(|byte| SpreadWord::<8, 16>::new(i2lebsp(byte.into())));
+        region.assign_advice(
+            || "tag",
+            self.lookup.tag,
+            row,
+            || spread_word.map(|word| F::from(word.tag as u64)),
+        )?;
+        byte.copy_advice(|| "byte", region, self.lookup.dense, row)?;
+        AssignedBits::<F, 16>::assign_bits(
+            region,
+            || "spread",
+            self.lookup.spread,
+            row,
+            spread_word.map(|word| word.spread),
+        )?;
+        self.lookup.assign_high(region, row, value.map(u16::from))?;
+
+        let shifted = value.map(|byte| SpreadWord::<16, 32>::new(i2lebsp((byte as u64) << 8)));
+        SpreadVar::with_lookup(region, &self.lookup, row + 1, shifted)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{super::Sha256, Table16Chip, Table16Config};
+    use crate::util::{pad_sha256_bytes, sha256_hash_bytes_digests};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
+    };
+    use halo2curves::bn256::Fr;
+
+    struct MyCircuit {
+        // Padded input, one field element per byte.
+        input: Vec<u64>,
+        expected: [u8; 32],
+    }
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = (Table16Config<Fr>, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+        #[cfg(feature = "circuit-params")]
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            MyCircuit {
+                input: vec![0; self.input.len()],
+                expected: self.expected,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let bytes = meta.advice_column();
+            meta.enable_equality(bytes);
+            (Table16Chip::configure(meta), bytes)
+        }
+
+        fn synthesize(
+            &self,
+            (config, bytes): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let table16_chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let input = layouter.assign_region(
+                || "input bytes",
+                |mut region| {
+                    self.input
+                        .iter()
+                        .enumerate()
+                        .map(|(i, byte)| {
+                            region.assign_advice(
+                                || format!("byte {}", i),
+                                bytes,
+                                i,
+                                || Value::known(Fr::from(*byte)),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                },
+            )?;
+
+            let digest = Sha256::digest_bytes(table16_chip, layouter.namespace(|| "digest"), &input)?;
+            for (word, expected) in digest.0.iter().zip(self.expected.chunks_exact(4)) {
+                word.0.assert_if_known(|word| word.to_be_bytes() == expected);
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn digest_bytes() {
+        let message = b"eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiIxMjM0NTY3ODkwIn0";
+        let circuit = MyCircuit {
+            input: pad_sha256_bytes(message).into_iter().map(u64::from).collect(),
+            expected: sha256_hash_bytes_digests(message),
+        };
+
+        let prover = match MockProver::<Fr>::run(17, &circuit, vec![]) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:?}", e),
+        };
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn digest_bytes_rejects_non_byte() {
+        // [0x00, 0x100, ...] packs to the same word as [0x01, 0x00, ...] and so yields the
+        // same digest, but the second "byte" is out of range.
+        let message = b"\x01\x00bc";
+        let mut input: Vec<u64> = pad_sha256_bytes(message).into_iter().map(u64::from).collect();
+        input[0] = 0;
+        input[1] = 0x100;
+        let circuit = MyCircuit {
+            input,
+            expected: sha256_hash_bytes_digests(message),
+        };
+
+        let prover = MockProver::<Fr>::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -1,23 +1,6 @@
 use halo2_proofs::circuit::Value;
 use crate::sha256::BlockWord;
 
-pub fn pad_bytes_front_n_end(origin: &[u8], front_pad: usize, end_pad: usize) -> Vec<u8> {
-    let mut result = Vec::new();
-    for _ in 0..front_pad {
-        result.push(0u8);
-    }
-
-    for i in origin {
-        result.push(* i);
-    }
-
-    for _ in 0..end_pad {
-        result.push(0u8);
-    }
-
-    result
-}
-
 pub fn usize_to_bytes(size: usize) -> [u8; 8] {
     let mut result = [0u8; 8];
     let mut remain = size;
@@ -29,9 +12,11 @@ pub fn usize_to_bytes(size: usize) -> [u8; 8] {
     result
 }
 
+/// Applies the SHA-256 padding to `origin`: a 1 bit, zeros, and the bit length as a
+/// big-endian u64, up to a whole number of 64-byte blocks.
 pub fn pad_sha256_bytes(origin: &[u8]) -> Vec<u8> {
     let len = origin.len();
-    let plen = len + 1 + 8; // one 0x80 and 4 bytes len
+    let plen = len + 1 + 8; // one 0x80 and 8 bytes len
     
     let blocks = (plen + 63) >> 6;
 
@@ -81,9 +66,10 @@ pub fn bytes_to_u32_array(origin: &[u8]) -> Vec<u32> {
     
     let mut result: Vec<u32> = Vec::new();
     loop {
-        if offset + 4 > len {
+        if offset >= len {
             break;
         } else if offset + 4 > len {
+            // the trailing bytes make a word, zero padded on the right
             result.push(
                 bytes_to_u32(&origin[offset..len])
             );
@@ -121,4 +107,103 @@ pub fn sha256_hash_bytes_digests(msg: &[u8]) -> [u8; 32] {
     let digest = hasher.finalize();
 
     digest.into()
-}
\ No newline at end of file
+}
+
+/// The SHA-256 state after compressing `prefix`, which must be a whole number of
+/// 64-byte blocks. Hashing can then resume in-circuit from this midstate.
+pub fn sha256_midstate(prefix: &[u8]) -> [u32; 8] {
+    use sha2::digest::generic_array::GenericArray;
+
+    assert!(prefix.len() % 64 == 0);
+
+    let blocks: Vec<_> = prefix
+        .chunks_exact(64)
+        .map(|block| *GenericArray::from_slice(block))
+        .collect();
+
+    let mut state = crate::sha256::IV;
+    sha2::compress256(&mut state, &blocks);
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bytes_to_u32, bytes_to_u32_array, find_subsequence_u8,
+        pad_sha256_bytes, sha256_hash_bytes_digests, sha256_midstate,
+    };
+
+    use proptest::prelude::*;
+    use sha2::{Digest, Sha256};
+
+    /// Messages of any length, with extra weight on the lengths around block boundaries,
+    /// where the padding spills into another block.
+    fn message() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![
+            prop::collection::vec(any::<u8>(), 0..300),
+            prop::sample::select(vec![0usize, 1, 55, 56, 57, 63, 64, 65, 119, 120, 127, 128])
+                .prop_flat_map(|len| prop::collection::vec(any::<u8>(), len)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn pad_sha256_bytes_is_sha256_padding(message in message()) {
+            let padded = pad_sha256_bytes(&message);
+
+            prop_assert_eq!(padded.len() % 64, 0);
+            // the fewest blocks holding the message, 0x80 and the length
+            prop_assert!(padded.len() >= message.len() + 9 && padded.len() < message.len() + 9 + 64);
+            prop_assert_eq!(&padded[..message.len()], &message[..]);
+            prop_assert_eq!(padded[message.len()], 0x80);
+            prop_assert!(padded[message.len() + 1..padded.len() - 8].iter().all(|byte| *byte == 0));
+            prop_assert_eq!(&padded[padded.len() - 8..], &((message.len() as u64) * 8).to_be_bytes()[..]);
+
+            // compressing the padded blocks from the IV is the SHA-256 digest
+            let digest = sha256_midstate(&padded)
+                .iter()
+                .flat_map(|word| word.to_be_bytes())
+                .collect::<Vec<_>>();
+            prop_assert_eq!(&digest[..], &Sha256::digest(&message)[..]);
+            prop_assert_eq!(&sha256_hash_bytes_digests(&message)[..], &Sha256::digest(&message)[..]);
+        }
+
+        #[test]
+        fn bytes_to_u32_array_keeps_every_byte(origin in message()) {
+            let words = bytes_to_u32_array(&origin);
+
+            prop_assert_eq!(words.len(), (origin.len() + 3) / 4);
+            for (word, chunk) in words.iter().zip(origin.chunks(4)) {
+                let mut expected = [0u8; 4];
+                expected[..chunk.len()].copy_from_slice(chunk);
+                prop_assert_eq!(*word, u32::from_be_bytes(expected));
+                prop_assert_eq!(*word, bytes_to_u32(chunk));
+            }
+
+            let bytes = words.iter().flat_map(|word| word.to_be_bytes()).collect::<Vec<_>>();
+            prop_assert_eq!(&bytes[..origin.len()], &origin[..]);
+            prop_assert!(bytes[origin.len()..].iter().all(|byte| *byte == 0));
+        }
+
+        #[test]
+        fn find_subsequence_u8_finds_the_first_occurrence(
+            haystack in prop::collection::vec(0u8..4, 1..64),
+            start in any::<prop::sample::Index>(),
+            len in 1usize..8,
+        ) {
+            let start = start.index(haystack.len());
+            let needle = &haystack[start..(start + len).min(haystack.len())];
+
+            let position = find_subsequence_u8(&haystack, needle).unwrap();
+            prop_assert!(position <= start);
+            prop_assert_eq!(&haystack[position..position + needle.len()], needle);
+            prop_assert!((0..position).all(|i| &haystack[i..i + needle.len()] != needle));
+        }
+    }
+
+    #[test]
+    fn bytes_to_u32_array_of_a_partial_word() {
+        assert_eq!(bytes_to_u32_array(&[0x01, 0x02, 0x03, 0x04, 0x05]), [0x01020304, 0x05000000]);
+        assert_eq!(bytes_to_u32_array(&[]), Vec::<u32>::new());
+    }
+}
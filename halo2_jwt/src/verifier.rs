@@ -0,0 +1,123 @@
+//! Verification of `JwtCircuit` proofs by a relying party, which holds the public
+//! parameters and the verifying key but none of the prover's witness.
+
+use halo2_proofs::plonk::VerifyingKey;
+
+use crate::bundle::{JwtProof, JwtPublicInputs};
+use crate::error::JwtError;
+use crate::proof::{Backend, ProvingConfig};
+use crate::util::sha256_hash_bytes_digests;
+
+/// What a verified proof establishes about the token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedClaims {
+    /// SHA-256 digest of the JWT.
+    pub jwt_digest: [u8; 32],
+    /// SHA-256 digest of the credential.
+    pub credential_digest: [u8; 32],
+    /// Big-endian modulus of the key the JWT is signed with, for `PS256` proofs.
+    pub modulus: Option<Vec<u8>>,
+}
+
+impl VerifiedClaims {
+    /// Whether the proven credential is `credential`, wherever it sits in the JWT.
+    pub fn is_credential(&self, credential: &[u8]) -> bool {
+        sha256_hash_bytes_digests(credential) == self.credential_digest
+    }
+
+    /// Whether the JWT is proven to be signed with the big-endian `modulus`.
+    pub fn is_signed_by(&self, modulus: &[u8]) -> bool {
+        let strip = |bytes: &[u8]| -> Vec<u8> { bytes.iter().copied().skip_while(|b| *b == 0).collect() };
+        self.modulus.as_deref().map(strip) == Some(strip(modulus))
+    }
+}
+
+impl From<JwtPublicInputs> for VerifiedClaims {
+    fn from(public_inputs: JwtPublicInputs) -> Self {
+        Self {
+            jwt_digest: public_inputs.jwt_digest,
+            credential_digest: public_inputs.credential_digest,
+            modulus: public_inputs.modulus,
+        }
+    }
+}
+
+/// Verifies a proof produced with [`ProvingConfig::NATIVE`] and decodes its claims.
+pub fn verify_jwt_proof<B: Backend>(
+    params: &B::Params,
+    vk: &VerifyingKey<B::Affine>,
+    public_inputs: &[B::Scalar],
+    proof: &[u8],
+) -> Result<VerifiedClaims, JwtError> {
+    verify_jwt_proof_with_config::<B>(params, vk, public_inputs, proof, &ProvingConfig::NATIVE)
+}
+
+/// Verifies a proof produced with `config` and decodes its claims.
+pub fn verify_jwt_proof_with_config<B: Backend>(
+    params: &B::Params,
+    vk: &VerifyingKey<B::Affine>,
+    public_inputs: &[B::Scalar],
+    proof: &[u8],
+    config: &ProvingConfig,
+) -> Result<VerifiedClaims, JwtError> {
+    let claims = JwtPublicInputs::from_instances(public_inputs)?;
+    if !B::verify(params, vk, &[public_inputs.to_vec()], proof, config) {
+        return Err(JwtError::ProofRejected);
+    }
+
+    Ok(claims.into())
+}
+
+/// Verifies a proof bundle produced with `config` and decodes its claims.
+pub fn verify_jwt_bundle<B: Backend>(
+    params: &B::Params,
+    vk: &VerifyingKey<B::Affine>,
+    bundle: &JwtProof,
+    config: &ProvingConfig,
+) -> Result<VerifiedClaims, JwtError> {
+    let public_inputs = bundle.public_inputs.to_instances::<B::Scalar>();
+    verify_jwt_proof_with_config::<B>(params, vk, &public_inputs, &bundle.proof, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_jwt_proof;
+    use crate::circuit::JwtCircuit;
+    use crate::error::JwtError;
+    use crate::precompute::PreComputed;
+    use crate::proof::{prove_jwt, Backend, Kzg, ProvingConfig};
+    use crate::util::sha256_hash_bytes_digests;
+    use halo2curves::bn256::Fr;
+
+    #[test]
+    fn verify_and_decode() {
+        let jwt = "{\"iss\":\"https://dev-9h47ajc9.us.au111th0.com/\",\"sub\":\"twitter|337834122\",\"aud\":\"123\"}";
+        let credential = "twitter|337834122";
+
+        let circuit = JwtCircuit::new(PreComputed::new(jwt, credential));
+        let public_inputs = circuit.public_inputs::<Fr>();
+
+        let params = Kzg::setup(17);
+        let pk = Kzg::keygen(&params, &circuit).unwrap();
+        let proof = prove_jwt::<Kzg>(&params, &pk, circuit, &ProvingConfig::NATIVE).unwrap();
+
+        let claims = verify_jwt_proof::<Kzg>(&params, pk.get_vk(), &public_inputs, &proof).unwrap();
+        assert_eq!(claims.jwt_digest, sha256_hash_bytes_digests(jwt.as_bytes()));
+        assert_eq!(claims.credential_digest, sha256_hash_bytes_digests(credential.as_bytes()));
+        assert!(claims.is_credential(credential.as_bytes()));
+        assert!(!claims.is_credential(b"twitter|337834123"));
+        assert_eq!(claims.modulus, None);
+
+        let mut tampered = proof.clone();
+        tampered[0] ^= 1;
+        assert!(matches!(
+            verify_jwt_proof::<Kzg>(&params, pk.get_vk(), &public_inputs, &tampered),
+            Err(JwtError::ProofRejected)
+        ));
+
+        assert!(matches!(
+            verify_jwt_proof::<Kzg>(&params, pk.get_vk(), &public_inputs[..15], &proof),
+            Err(JwtError::InvalidPublicInputs)
+        ));
+    }
+}